@@ -0,0 +1,112 @@
+use std::hint::black_box;
+
+use bevy::math::IVec3;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+#[cfg(feature = "client")]
+use dirlaku::chunk::chunk::{Chunk, CHUNK_SIZE};
+#[cfg(feature = "client")]
+use dirlaku::chunk::mesh::bake;
+use dirlaku::position::universe_transform::UniverseTransform;
+use dirlaku::terrain::terraingen::generate_chunk;
+use dirlaku::world::block::register_default_blocks;
+use dirlaku::world::universe::Universe;
+
+// a throwaway world, wiped every run so old chunks don't skew anything
+fn bench_universe() -> Universe {
+    let path = std::env::temp_dir().join("dirlaku-bench");
+    let _ = std::fs::remove_dir_all(&path);
+    let universe = Universe::open(path);
+    register_default_blocks(&universe);
+    universe
+}
+
+// every other block filled, so every face of every block is visible
+#[cfg(feature = "client")]
+fn checkerboard_chunk(universe: &Universe) -> Chunk {
+    let stone = universe.block_id_from_name(String::from("stone"));
+    let mut chunk = Chunk::new();
+    for x in 0..CHUNK_SIZE as u32 {
+        for y in 0..CHUNK_SIZE as u32 {
+            for z in 0..CHUNK_SIZE as u32 {
+                if (x + y + z) % 2 == 0 {
+                    chunk.place(stone, (x, y, z));
+                }
+            }
+        }
+    }
+    chunk
+}
+
+fn generation(c: &mut Criterion) {
+    let universe = bench_universe();
+    let mut group = c.benchmark_group("generate_chunk");
+    let coords = [
+        ("surface", IVec3::new(0, 0, 0)),
+        ("underground", IVec3::new(0, -3, 0)),
+        ("sky", IVec3::new(0, 4, 0)),
+        ("far", IVec3::new(10_000, 0, -10_000)),
+    ];
+    for (name, pos) in coords {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &pos, |b, pos| {
+            b.iter(|| generate_chunk(&universe, black_box(*pos)))
+        });
+    }
+    group.finish();
+}
+
+// meshing needs bevy's render types, everything else here runs headless too
+#[cfg(feature = "client")]
+fn meshing(c: &mut Criterion) {
+    let universe = bench_universe();
+    let mut group = c.benchmark_group("bake");
+    let chunks = [
+        ("checkerboard", checkerboard_chunk(&universe)),
+        ("typical", generate_chunk(&universe, IVec3::ZERO)),
+        ("empty", Chunk::new()),
+    ];
+    for (name, chunk) in &chunks {
+        group.bench_with_input(BenchmarkId::from_parameter(name), chunk, |b, chunk| {
+            b.iter(|| bake(&universe, black_box(chunk)))
+        });
+    }
+    group.finish();
+}
+
+fn database(c: &mut Criterion) {
+    let universe = bench_universe();
+    let chunk = generate_chunk(&universe, IVec3::ZERO);
+    let coords = IVec3::new(3, 0, -2);
+    c.bench_function("flush_fetch_roundtrip", |b| {
+        b.iter(|| {
+            universe.flush_chunk(black_box(&coords), &chunk);
+            universe.fetch_chunk(black_box(&coords))
+        })
+    });
+}
+
+fn raycasting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("integer_raycast");
+    // straight along an axis, and at an angle that crosses all three axes a lot
+    let mut diagonal = UniverseTransform::from_dim_xyz(0, (0.5, 40.5, 0.5));
+    diagonal.pitch = -0.6;
+    diagonal.yaw = 0.8;
+    let transforms = [
+        ("axis", UniverseTransform::from_dim_xyz(0, (0.5, 40.5, 0.5))),
+        ("diagonal", diagonal),
+    ];
+    for (name, transform) in &transforms {
+        for range in [8.0, 64.0] {
+            group.bench_with_input(BenchmarkId::new(*name, range), &range, |b, range| {
+                b.iter(|| transform.integer_raycast(black_box(*range)))
+            });
+        }
+    }
+    group.finish();
+}
+
+#[cfg(feature = "client")]
+criterion_group!(benches, generation, meshing, database, raycasting);
+#[cfg(not(feature = "client"))]
+criterion_group!(benches, generation, database, raycasting);
+criterion_main!(benches);
@@ -0,0 +1,50 @@
+// a dedicated server, owns the world and lets game clients connect to it
+// usage: dirlaku-server [address] [game mode], clients join with `dirlaku connect [address]`
+
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+
+use dirlaku::gamemode::GameMode;
+use dirlaku::net::server::{Server, ServerPlugin, TICK_RATE};
+use dirlaku::net::DEFAULT_ADDRESS;
+use dirlaku::world::block::register_default_blocks;
+use dirlaku::world::edits::BlockEventsPlugin;
+use dirlaku::world::fluid::FluidPlugin;
+use dirlaku::world::universe::Universe;
+
+fn main() {
+    let address = std::env::args().nth(1).unwrap_or_else(|| String::from(DEFAULT_ADDRESS));
+
+    let game_mode = match std::env::args().nth(2) {
+        Some(name) => GameMode::from_name(&name).unwrap_or_else(|| {
+            eprintln!("Unknown game mode \"{}\"", name);
+            std::process::exit(1);
+        }),
+        None => GameMode::default(),
+    };
+
+    let mut server = match Server::bind(&address) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Couldn't listen on {}: {}", address, e);
+            std::process::exit(1);
+        }
+    };
+    server.game_mode = game_mode;
+    println!("Listening on {} in {}", address, game_mode.name());
+
+    let universe = Universe::new();
+    register_default_blocks(&universe);
+
+    App::new()
+        // no window to wait on, so the loop runs at the tick rate instead of flat out
+        .add_plugins(MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(1.0 / TICK_RATE))))
+        .add_plugins(LogPlugin::default())
+        .add_plugins((ServerPlugin, BlockEventsPlugin, FluidPlugin))
+        .insert_resource(universe)
+        .insert_resource(server)
+        .run();
+}
@@ -0,0 +1,5 @@
+// the world tools on their own, builds without the client feature so it runs on machines without a gpu
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(dirlaku::cli::run(&args));
+}
@@ -1,2 +1,3 @@
 pub mod chunk;
+#[cfg(feature = "client")]
 pub mod mesh;
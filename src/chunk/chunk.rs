@@ -1,4 +1,4 @@
-use bevy::prelude::Component;
+use bevy::prelude::{Component, IVec3, UVec3};
 
 use zerocopy::{
         AsBytes, FromBytes, FromZeroes
@@ -6,12 +6,18 @@ use zerocopy::{
 
 pub const CHUNK_SIZE: usize = 32;
 pub const CHUNK_SIZE_I32: i32 = CHUNK_SIZE as i32;
-#[derive(Clone, Copy, PartialEq, Eq, Hash, FromBytes, FromZeroes, AsBytes)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, FromBytes, FromZeroes, AsBytes)]
 #[repr(C)]
 pub struct BlockId(pub u32);
 
 pub const AIR: BlockId = BlockId(0);
 
+// splits an integer block position into the chunk it is in and its position within that chunk
+pub fn split_block_position(pos: IVec3) -> (IVec3, UVec3) {
+    let size = IVec3::splat(CHUNK_SIZE_I32);
+    (pos.div_euclid(size), pos.rem_euclid(size).as_uvec3())
+}
+
 #[derive(Component, FromBytes, FromZeroes, AsBytes)]
 #[repr(C)]
 pub struct Chunk {
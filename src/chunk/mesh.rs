@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use super::chunk::BlockId;
 use bevy::{
-    prelude::Mesh,
+    prelude::{IVec3, Mesh, Vec3},
     render::{
         mesh::{Indices, VertexAttributeValues},
         render_resource::PrimitiveTopology,
@@ -15,16 +15,21 @@ use block_mesh::{
     GreedyQuadsBuffer, MergeVoxel, Voxel, VoxelVisibility, RIGHT_HANDED_Y_UP_CONFIG,
 };
 
-use super::chunk::{Chunk, AIR};
+use super::chunk::{Chunk, AIR, CHUNK_SIZE_I32};
+use crate::world::block::{BlockShape, BlockType, ShapeBox};
+use crate::world::fluid::{fluid_state, FluidState};
 use crate::world::universe::Universe;
 
 struct MeshVoxel {
     id: BlockId,
+    // the block's first state, so faces between two states of the same translucent block get culled too
+    base: BlockId,
     vis: VoxelVisibility,
 }
 
 const AIRVOXEL: MeshVoxel = MeshVoxel {
     id: AIR,
+    base: AIR,
     vis: VoxelVisibility::Empty,
 };
 
@@ -80,15 +85,28 @@ impl MergeVoxel for MeshVoxel {
 
     type MergeValueFacingNeighbour = Self::MergeValue;
     fn merge_value_facing_neighbour(&self) -> Self::MergeValueFacingNeighbour {
-        return self.merge_value();
+        return self.base;
     }
 }
 
 const CHUNK_MESH_SIZE: u32 = 32 + 2;
 type ChunkMeshShape = ConstShape3u32<CHUNK_MESH_SIZE, CHUNK_MESH_SIZE, CHUNK_MESH_SIZE>;
 
-pub fn bake(universe: &Universe, chunk: &Chunk) -> HashMap<BlockId, Mesh> {
+const HALF_CHUNK: f32 = CHUNK_SIZE_I32 as f32 / 2.0;
+
+// a chunk's meshes, one per block state
+pub struct ChunkMeshes {
+    pub opaque: HashMap<BlockId, Mesh>,
+    // blocks with translucent visibility, drawn alpha blended
+    // these are centered on the chunk instead of starting at its corner,
+    // since bevy sorts blended meshes back to front by where their transform is
+    pub translucent: HashMap<BlockId, Mesh>,
+}
+
+pub fn bake(universe: &Universe, chunk: &Chunk) -> ChunkMeshes {
     let mut voxels = [AIRVOXEL; ChunkMeshShape::SIZE as usize];
+    // blocks that aren't cubes, meshed after the greedy pass
+    let mut shaped = Vec::new();
 
     for i in 0..ChunkMeshShape::SIZE {
         let [x, y, z] = ChunkMeshShape::delinearize(i);
@@ -103,9 +121,18 @@ pub fn bake(universe: &Universe, chunk: &Chunk) -> HashMap<BlockId, Mesh> {
         }
         let block_id = chunk.get(x - 1, y - 1, z - 1);
         let block = universe.get_block_data_id(block_id);
+        // other shapes don't hide their neighbours' faces, so greedy meshing treats them as air
+        // fluids have sloped surfaces, so they're meshed with the other shapes
+        if !block.shape.is_cube() || matches!(block.block_type, BlockType::Fluid) {
+            shaped.push((IVec3::new(x as i32 - 1, y as i32 - 1, z as i32 - 1), block_id));
+            continue;
+        }
+        let vis = block.block_type.get_visibility();
         voxels[i as usize] = MeshVoxel {
             id: block_id,
-            vis: block.block_type.get_visibility(),
+            // only translucent faces look at this, and looking it up isn't free
+            base: if vis == VoxelVisibility::Translucent { universe.base_id(block_id) } else { block_id },
+            vis,
         };
     }
     let mut buffer = GreedyQuadsBuffer::new(voxels.len());
@@ -119,6 +146,7 @@ pub fn bake(universe: &Universe, chunk: &Chunk) -> HashMap<BlockId, Mesh> {
     );
 
     let mut premeshes = HashMap::new();
+    let mut translucent = HashMap::new();
     /*
     let num_indices = buffer.quads.num_quads() * 6;
     let num_vertices = buffer.quads.num_quads() * 4;
@@ -141,9 +169,10 @@ pub fn bake(universe: &Universe, chunk: &Chunk) -> HashMap<BlockId, Mesh> {
     {
         for quad in group.iter() {
             let min_xyz = quad.minimum;
-            let block_id = chunk.get(min_xyz[0] - 1, min_xyz[1] - 1, min_xyz[2] - 1);
+            let voxel = &voxels[ChunkMeshShape::linearize(min_xyz) as usize];
+            let block_id = voxel.id;
 
-            let premesh = premeshes
+            let premesh = if voxel.vis == VoxelVisibility::Translucent { &mut translucent } else { &mut premeshes }
                 .entry(block_id)
                 .or_insert_with(|| PreMesh::default());
 
@@ -168,8 +197,193 @@ pub fn bake(universe: &Universe, chunk: &Chunk) -> HashMap<BlockId, Mesh> {
         }
     }
 
-    return premeshes
-        .into_iter()
-        .map(|(k, value)| (k, value.construct()))
-        .collect();
+    let mut fluids = FluidCache::default();
+    for (pos, block_id) in shaped {
+        let vis = universe.get_block_data_id(block_id).block_type.get_visibility();
+        let premesh = if vis == VoxelVisibility::Translucent { &mut translucent } else { &mut premeshes }
+            .entry(block_id)
+            .or_insert_with(|| PreMesh::default());
+        match fluids.get(universe, chunk, pos) {
+            Some(state) => bake_fluid(universe, chunk, pos, state, &mut fluids, premesh),
+            None => bake_shaped(universe, chunk, pos, block_id, premesh),
+        }
+    }
+
+    ChunkMeshes {
+        opaque: premeshes
+            .into_iter()
+            .map(|(k, value)| (k, value.construct()))
+            .collect(),
+        translucent: translucent
+            .into_iter()
+            .map(|(k, mut value)| {
+                for v in value.vertices.iter_mut() {
+                    *v = v.map(|x| x - HALF_CHUNK);
+                }
+                (k, value.construct())
+            })
+            .collect(),
+    }
+}
+
+// whether a block in the chunk completely covers the face of its neighbour
+// outside the chunk we don't know, so those faces get drawn, same as in the greedy pass
+fn covers_face(universe: &Universe, chunk: &Chunk, pos: IVec3) -> bool {
+    if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(CHUNK_SIZE_I32)).any() {
+        return false;
+    }
+    let block = universe.get_block_data_id(chunk.get(pos.x as u32, pos.y as u32, pos.z as u32));
+    block.shape.is_cube() && matches!(block.block_type, BlockType::OpaqueSolid)
+}
+
+// adds a quad to the mesh, wound so it faces where normal points
+// corners go around the quad, uvs are in blocks so the texture repeats instead of stretching
+fn push_quad(premesh: &mut PreMesh, corners: [Vec3; 4], normal: Vec3, uvs: [[f32; 2]; 4]) {
+    let start = premesh.vertices.len() as u32;
+    let facing = (corners[1] - corners[0]).cross(corners[2] - corners[0]).dot(normal) > 0.0;
+    let order = if facing { [0, 1, 2, 0, 2, 3] } else { [0, 2, 1, 0, 3, 2] };
+    premesh.indices.extend(order.map(|i| start + i));
+    premesh.vertices.extend(corners.map(|c| c.to_array()));
+    premesh.normals.extend([normal.to_array(); 4]);
+    premesh.uvs.extend_from_slice(&uvs);
+}
+
+// the texture is laid out the same way on every box, so a slab shows the bottom half of it on its sides
+fn face_uv(local: Vec3, axis: usize) -> [f32; 2] {
+    match axis {
+        1 => [local.x, local.z],
+        0 => [local.z, 1.0 - local.y],
+        _ => [local.x, 1.0 - local.y],
+    }
+}
+
+fn bake_box(universe: &Universe, chunk: &Chunk, pos: IVec3, b: &ShapeBox, premesh: &mut PreMesh) {
+    let corner = pos.as_vec3();
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        for positive in [false, true] {
+            // faces on the edge of the block are hidden by a full neighbour
+            let on_edge = if positive { b.max[axis] >= 1.0 } else { b.min[axis] <= 0.0 };
+            let mut step = IVec3::ZERO;
+            step[axis] = if positive { 1 } else { -1 };
+            if on_edge && covers_face(universe, chunk, pos + step) {
+                continue;
+            }
+
+            let mut local = [Vec3::ZERO; 4];
+            for (i, (cu, cv)) in [(false, false), (true, false), (true, true), (false, true)].into_iter().enumerate() {
+                local[i][axis] = if positive { b.max[axis] } else { b.min[axis] };
+                local[i][u] = if cu { b.max[u] } else { b.min[u] };
+                local[i][v] = if cv { b.max[v] } else { b.min[v] };
+            }
+            push_quad(premesh, local.map(|l| corner + l), step.as_vec3(), local.map(|l| face_uv(l, axis)));
+        }
+    }
+}
+
+fn bake_shaped(universe: &Universe, chunk: &Chunk, pos: IVec3, block_id: BlockId, premesh: &mut PreMesh) {
+    let shape = universe.get_block_data_id(block_id).shape.clone();
+    match shape {
+        BlockShape::Cross => {
+            // two diagonal planes, each drawn from both sides
+            let corner = pos.as_vec3();
+            let (lo, hi) = (0.15, 0.85);
+            for (from, to) in [([lo, lo], [hi, hi]), ([lo, hi], [hi, lo])] {
+                let quad = [
+                    Vec3::new(from[0], 0.0, from[1]),
+                    Vec3::new(to[0], 0.0, to[1]),
+                    Vec3::new(to[0], 1.0, to[1]),
+                    Vec3::new(from[0], 1.0, from[1]),
+                ];
+                let normal = (quad[1] - quad[0]).cross(Vec3::Y).normalize();
+                let uvs = [[0.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0]];
+                push_quad(premesh, quad.map(|c| corner + c), normal, uvs);
+                push_quad(premesh, quad.map(|c| corner + c), -normal, uvs);
+            }
+        }
+        _ => {
+            for b in universe.block_boxes(block_id) {
+                bake_box(universe, chunk, pos, &b, premesh);
+            }
+        }
+    }
+}
+
+// fluid states by block, a lake is the same few states over and over and looking them up isn't free
+#[derive(Default)]
+struct FluidCache(HashMap<BlockId, Option<FluidState>>);
+
+impl FluidCache {
+    // None outside the chunk too
+    fn get(&mut self, universe: &Universe, chunk: &Chunk, pos: IVec3) -> Option<FluidState> {
+        if pos.cmplt(IVec3::ZERO).any() || pos.cmpge(IVec3::splat(CHUNK_SIZE_I32)).any() {
+            return None;
+        }
+        let id = chunk.get(pos.x as u32, pos.y as u32, pos.z as u32);
+        *self.0.entry(id).or_insert_with(|| fluid_state(universe, id))
+    }
+
+    fn same(&mut self, universe: &Universe, chunk: &Chunk, pos: IVec3, fluid: BlockId) -> Option<FluidState> {
+        self.get(universe, chunk, pos).filter(|f| f.fluid == fluid)
+    }
+}
+
+// the surface slopes down towards where the fluid is flowing, each top corner is the average height of the
+// blocks of the same fluid around it, or full if any of them has more of the fluid on top
+// faces against the same fluid or a full opaque block aren't drawn
+fn bake_fluid(universe: &Universe, chunk: &Chunk, pos: IVec3, state: FluidState, fluids: &mut FluidCache, premesh: &mut PreMesh) {
+    let covered = fluids.same(universe, chunk, pos + IVec3::Y, state.fluid).is_some();
+    let mut heights = [[1.0f32; 2]; 2];
+    if !covered {
+        for (cx, cz) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            let (mut total, mut count) = (0.0, 0);
+            let mut full = false;
+            for (dx, dz) in [(cx - 1, cz - 1), (cx, cz - 1), (cx - 1, cz), (cx, cz)] {
+                let p = pos + IVec3::new(dx, 0, dz);
+                if let Some(f) = fluids.same(universe, chunk, p, state.fluid) {
+                    full |= fluids.same(universe, chunk, p + IVec3::Y, state.fluid).is_some();
+                    total += f.height();
+                    count += 1;
+                }
+            }
+            // the block itself is always one of them
+            heights[cx as usize][cz as usize] = if full { 1.0 } else { total / count as f32 };
+        }
+    }
+
+    let corner = pos.as_vec3();
+    let top = |x: usize, z: usize| Vec3::new(x as f32, heights[x][z], z as f32);
+
+    if !covered {
+        // drawn from both sides so the surface can be seen from under it
+        let quad = [top(0, 0), top(1, 0), top(1, 1), top(0, 1)];
+        let uvs = quad.map(|l| face_uv(l, 1));
+        push_quad(premesh, quad.map(|c| corner + c), Vec3::Y, uvs);
+        push_quad(premesh, quad.map(|c| corner + c), Vec3::NEG_Y, uvs);
+    }
+
+    let below = pos - IVec3::Y;
+    if fluids.same(universe, chunk, below, state.fluid).is_none() && !covers_face(universe, chunk, below) {
+        let quad = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0), Vec3::Z];
+        push_quad(premesh, quad.map(|c| corner + c), Vec3::NEG_Y, quad.map(|l| face_uv(l, 1)));
+    }
+
+    for d in [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z] {
+        let n = pos + d;
+        if fluids.same(universe, chunk, n, state.fluid).is_some() || covers_face(universe, chunk, n) {
+            continue;
+        }
+        // the two top corners along this side
+        let (a, b) = if d.x != 0 {
+            let x = (d.x > 0) as usize;
+            ((x, 0), (x, 1))
+        } else {
+            let z = (d.z > 0) as usize;
+            ((0, z), (1, z))
+        };
+        let bottom = |(x, z): (usize, usize)| Vec3::new(x as f32, 0.0, z as f32);
+        let quad = [bottom(a), bottom(b), top(b.0, b.1), top(a.0, a.1)];
+        let axis = if d.x != 0 { 0 } else { 2 };
+        push_quad(premesh, quad.map(|c| corner + c), d.as_vec3(), quad.map(|l| face_uv(l, axis)));
+    }
 }
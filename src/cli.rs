@@ -0,0 +1,146 @@
+// command line tools that work on the world database directly, without starting the game
+// these don't need rendering, so they're also what the headless dirlaku-world binary runs
+// the game must not be running at the same time, sled only allows one process to open the database
+
+use std::path::PathBuf;
+
+use bevy::prelude::IVec3;
+
+use crate::chunk::chunk::split_block_position;
+use crate::schematic::{export_file, import_file, BlockMapping, DEFAULT_MAPPING};
+use crate::terrain::terraingen::generate_chunk;
+use crate::world::block::register_default_blocks;
+use crate::world::edits::EditCause;
+use crate::world::region::Region;
+use crate::world::universe::Universe;
+
+const USAGE: &str = "\
+usage:
+  dirlaku schem export <x1> <y1> <z1> <x2> <y2> <z2> <file.schem|file.vox> [--mapping <table>]
+  dirlaku schem import <file.schem|file.vox> <x> <y> <z> [--mapping <table>] [--keep-air]
+  dirlaku generate <cx1> <cy1> <cz1> <cx2> <cy2> <cz2>";
+
+// pulls `--flag value` out of the argument list
+fn take_option(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|a| a == flag) {
+        None => Ok(None),
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        }
+        Some(_) => Err(format!("{} needs a value", flag)),
+    }
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        None => false,
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+    }
+}
+
+fn parse_coords(args: &[String]) -> Result<IVec3, String> {
+    let n = |s: &String| s.parse::<i32>().map_err(|_| format!("\"{}\" is not a block coordinate", s));
+    Ok(IVec3::new(n(&args[0])?, n(&args[1])?, n(&args[2])?))
+}
+
+fn load_mapping(path: Option<String>) -> Result<BlockMapping, String> {
+    match path {
+        Some(p) => BlockMapping::load(&PathBuf::from(&p)).map_err(|e| format!("could not read mapping {}: {}", p, e)),
+        None => BlockMapping::parse(DEFAULT_MAPPING).map_err(|e| e.to_string()),
+    }
+}
+
+fn open_universe() -> Universe {
+    let universe = Universe::new();
+    register_default_blocks(&universe);
+    universe
+}
+
+// generates every chunk in the box that doesn't exist yet, returning how many that was
+fn generate_missing(universe: &Universe, chunks: &Region) -> usize {
+    let mut generated = 0;
+    for chunk_pos in chunks.iter() {
+        if !universe.chunk_generated(&chunk_pos) {
+            universe.flush_chunk(&chunk_pos, &generate_chunk(universe, chunk_pos));
+            generated += 1;
+        }
+    }
+    generated
+}
+
+fn schem_export(mut args: Vec<String>) -> Result<(), String> {
+    let mapping = load_mapping(take_option(&mut args, "--mapping")?)?;
+    if args.len() != 7 {
+        return Err(String::from(USAGE));
+    }
+    let region = Region::from_corners(parse_coords(&args[0..3])?, parse_coords(&args[3..6])?);
+    let path = PathBuf::from(&args[6]);
+
+    let universe = open_universe();
+    let clipboard = universe.copy_region(&region);
+    export_file(&path, &universe, &clipboard, &mapping).map_err(|e| format!("export failed: {}", e))?;
+
+    let size = region.size();
+    println!("Exported {}x{}x{} blocks to {}", size.x, size.y, size.z, path.display());
+    Ok(())
+}
+
+fn schem_import(mut args: Vec<String>) -> Result<(), String> {
+    let mapping = load_mapping(take_option(&mut args, "--mapping")?)?;
+    let keep_air = take_flag(&mut args, "--keep-air");
+    if args.len() != 4 {
+        return Err(String::from(USAGE));
+    }
+    let path = PathBuf::from(&args[0]);
+    let origin = parse_coords(&args[1..4])?;
+
+    let universe = open_universe();
+    let clipboard = import_file(&path, &universe, &mapping).map_err(|e| format!("import failed: {}", e))?;
+
+    // pasting skips chunks that don't exist yet, so generate everything the build covers first
+    let region = clipboard.region_at(origin);
+    let (min_chunk, _) = split_block_position(region.min);
+    let (max_chunk, _) = split_block_position(region.max);
+    generate_missing(&universe, &Region::from_corners(min_chunk, max_chunk));
+
+    let touched = universe.paste(&clipboard, origin, !keep_air, EditCause::Command);
+    let size = clipboard.size;
+    println!("Imported {}x{}x{} blocks from {} ({} chunks changed)", size.x, size.y, size.z, path.display(), touched.len());
+    Ok(())
+}
+
+// pregenerates a box of chunks, given in chunk coordinates rather than block coordinates
+fn generate(args: Vec<String>) -> Result<(), String> {
+    if args.len() != 6 {
+        return Err(String::from(USAGE));
+    }
+    let chunks = Region::from_corners(parse_coords(&args[0..3])?, parse_coords(&args[3..6])?);
+
+    let universe = open_universe();
+    let generated = generate_missing(&universe, &chunks);
+    println!("Generated {} chunks ({} already existed)", generated, chunks.volume() - generated);
+    Ok(())
+}
+
+// runs the tool named by the arguments, returning the process exit code
+pub fn run(args: &[String]) -> i32 {
+    let result = match args.iter().map(|s| s.as_str()).collect::<Vec<_>>().as_slice() {
+        ["schem", "export", ..] => schem_export(args[2..].to_vec()),
+        ["schem", "import", ..] => schem_import(args[2..].to_vec()),
+        ["generate", ..] => generate(args[1..].to_vec()),
+        _ => Err(String::from(USAGE)),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use bevy::math::f64::DVec3;
+use bevy::prelude::*;
+use std::sync::Arc;
+
+use super::{
+    parse_block_position, parse_int, parse_position, Arg, ArgKind, CommandResult, ConsoleCommand,
+    ConsoleCommands, ConsoleState, RegisterConsoleCommand,
+};
+use crate::chunk::chunk::{BlockId, CHUNK_SIZE_I32};
+use crate::physics::PhysicsBody;
+use crate::player::gamemode::GameMode;
+use crate::player::ThisPlayer;
+use crate::position::interpolation::TickInterpolation;
+use crate::position::universe_location::UniverseLocation;
+use crate::position::universe_transform::UniverseTransform;
+use crate::settings::Settings;
+use crate::terrain::terraingen::generate_chunk;
+use crate::world::edits::EditCause;
+use crate::world::region::Region;
+use crate::world::universe::Universe;
+
+// so a typo can't lock the game up for minutes
+const MAX_FILL_VOLUME: usize = 1 << 22;
+// much faster and the fixed timestep can't run enough ticks per frame to keep up
+const MAX_TIME_SPEED: f64 = 10.0;
+
+fn player_transform(world: &mut World) -> UniverseTransform {
+    world.query_filtered::<&UniverseTransform, With<ThisPlayer>>().single(world).clone()
+}
+
+fn lookup_block(universe: &Universe, name: &str) -> Result<BlockId, String> {
+    universe.try_block_id_from_name(name).ok_or_else(|| format!("Unknown block \"{}\"", name))
+}
+
+fn help(world: &mut World, args: &[&str]) -> CommandResult {
+    let commands = world.resource::<ConsoleCommands>();
+    if let Some(name) = args.first() {
+        let command = commands.get(name).ok_or_else(|| format!("Unknown command \"{}\"", name))?;
+        return Ok(format!("{}\n  {}", command.usage(), command.help));
+    }
+    let lines: Vec<String> = commands.iter().map(|c| format!("{} - {}", c.usage(), c.help)).collect();
+    Ok(lines.join("\n"))
+}
+
+fn clear(world: &mut World, _args: &[&str]) -> CommandResult {
+    world.resource_mut::<ConsoleState>().clear();
+    Ok(String::new())
+}
+
+fn tp(world: &mut World, args: &[&str]) -> CommandResult {
+    let current = player_transform(world);
+    let position = parse_position(args, current.loc.position)?;
+    let dimension = match args.get(3) {
+        Some(d) => d.parse::<u32>().map_err(|_| format!("dim should be a dimension number, not \"{}\"", d))?,
+        None => current.loc.dimension,
+    };
+
+    let mut query = world.query_filtered::<(&mut UniverseTransform, Option<&mut TickInterpolation>, Option<&mut PhysicsBody>), With<ThisPlayer>>();
+    let (mut trans, interp, body) = query.single_mut(world);
+    trans.loc = UniverseLocation::from_dim_xyz(dimension, position);
+    // teleports shouldn't be smoothed over, or keep the speed from before
+    if let Some(mut interp) = interp {
+        interp.snap_to(position);
+    }
+    if let Some(mut body) = body {
+        body.velocity = DVec3::ZERO;
+    }
+    Ok(format!("Teleported to {:.2} {:.2} {:.2} in dimension {}", position.x, position.y, position.z, dimension))
+}
+
+fn setblock(world: &mut World, args: &[&str]) -> CommandResult {
+    let origin = player_transform(world).loc.position;
+    let pos = parse_block_position(args, origin)?;
+    let universe = world.resource::<Universe>().clone();
+    let block = lookup_block(&universe, args[3])?;
+
+    if universe.set_block(pos, block, EditCause::Command).is_none() {
+        return Err(String::from("That chunk hasn't been generated yet"));
+    }
+    Ok(format!("Set {} {} {} to {}", pos.x, pos.y, pos.z, args[3]))
+}
+
+fn fill(world: &mut World, args: &[&str]) -> CommandResult {
+    let origin = player_transform(world).loc.position;
+    let a = parse_block_position(&args[0..3], origin)?;
+    let b = parse_block_position(&args[3..6], origin)?;
+    let universe = world.resource::<Universe>().clone();
+    let block = lookup_block(&universe, args[6])?;
+
+    let region = Region::from_corners(a, b);
+    if region.volume() > MAX_FILL_VOLUME {
+        return Err(format!("That's {} blocks, the most at once is {}", region.volume(), MAX_FILL_VOLUME));
+    }
+    universe.fill_region(&region, block, EditCause::Command);
+    Ok(format!("Filled {} blocks with {}", region.volume(), args[6]))
+}
+
+// game time stops while paused, and speeding it up speeds up everything on the fixed timestep with it
+fn time(world: &mut World, args: &[&str]) -> CommandResult {
+    let mut time = world.resource_mut::<Time<Virtual>>();
+    if let Some(arg) = args.first() {
+        match arg.parse::<f64>() {
+            Ok(speed) if speed > 0.0 && speed <= MAX_TIME_SPEED => time.set_relative_speed_f64(speed),
+            _ => return Err(format!("speed should be above 0 and at most {}, not \"{}\"", MAX_TIME_SPEED, arg)),
+        }
+    }
+    let seconds = time.elapsed().as_secs();
+    Ok(format!(
+        "Game time {}:{:02}:{:02}, running at {}x speed",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        time.relative_speed_f64()
+    ))
+}
+
+fn seed(world: &mut World, _args: &[&str]) -> CommandResult {
+    Ok(format!("Seed: {}", world.resource::<Universe>().seed))
+}
+
+fn regen(world: &mut World, args: &[&str]) -> CommandResult {
+    // only "chunk" for now, run_command has already checked that's what args[0] is
+    let coords = if args.len() > 1 {
+        if args.len() != 4 {
+            return Err(String::from("Give all three chunk coordinates, or none for the one you're in"));
+        }
+        IVec3::new(parse_int(args[1], "x")?, parse_int(args[2], "y")?, parse_int(args[3], "z")?)
+    } else {
+        player_transform(world).get_chunk_position()
+    };
+
+    let universe = world.resource::<Universe>().clone();
+    let generated = generate_chunk(&universe, coords);
+    if !universe.chunk_generated(&coords) {
+        universe.flush_chunk(&coords, &generated);
+        return Ok(format!("Generated chunk {} {} {}", coords.x, coords.y, coords.z));
+    }
+    // written as an edit, so whatever else follows edits (remeshing, the server) sees it too
+    let origin = coords * CHUNK_SIZE_I32;
+    let whole = Region::from_corners(origin, origin + IVec3::splat(CHUNK_SIZE_I32 - 1));
+    universe.edit_region(&whole, EditCause::Command, |pos, _| {
+        let local = (pos - origin).as_uvec3();
+        Some(generated.get(local.x, local.y, local.z))
+    });
+    universe.clear_block_entities(&coords);
+    Ok(format!("Regenerated chunk {} {} {}", coords.x, coords.y, coords.z))
+}
+
+fn gamemode(world: &mut World, args: &[&str]) -> CommandResult {
+    let mut query = world.query_filtered::<&mut GameMode, With<ThisPlayer>>();
+    let mut mode = query.single_mut(world);
+    let Some(name) = args.first() else {
+        return Ok(format!("Game mode: {}", mode.name()));
+    };
+    *mode = GameMode::from_name(name).ok_or_else(|| format!("Unknown game mode \"{}\"", name))?;
+    Ok(format!("Game mode set to {}", mode.name()))
+}
+
+fn render_distance(world: &mut World, args: &[&str]) -> CommandResult {
+    let mut settings = world.resource_mut::<Settings>();
+    if args.is_empty() {
+        return Ok(format!(
+            "Render distance: {} horizontal, {} vertical",
+            settings.horizontal_render_distance, settings.vertical_render_distance
+        ));
+    }
+
+    let parse = |arg: &str, name: &str| -> Result<u8, String> {
+        match arg.parse::<u8>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("{} should be from 1 to 255, not \"{}\"", name, arg)),
+        }
+    };
+    settings.horizontal_render_distance = parse(args[0], "horizontal")?;
+    if let Some(v) = args.get(1) {
+        settings.vertical_render_distance = parse(v, "vertical")?;
+    }
+    settings.save_or_warn();
+    Ok(format!(
+        "Render distance set to {} horizontal, {} vertical",
+        settings.horizontal_render_distance, settings.vertical_render_distance
+    ))
+}
+
+// the argument lists are in const blocks so they live forever, Arg::required isn't promoted to 'static on its own
+pub fn register_builtin_commands(app: &mut App) {
+    use ArgKind::{Block, Coord, Int, Literal, Number};
+    app.register_console_command(ConsoleCommand {
+        name: "help",
+        help: "Lists commands, or explains one",
+        args: const { &[Arg::optional("command", ArgKind::Command)] },
+        run: Arc::new(help),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "clear",
+        help: "Clears the console",
+        args: &[],
+        run: Arc::new(clear),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "tp",
+        help: "Teleports you, ~ means where you are now",
+        args: const { &[
+            Arg::required("x", Coord),
+            Arg::required("y", Coord),
+            Arg::required("z", Coord),
+            Arg::optional("dim", Int),
+        ] },
+        run: Arc::new(tp),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "setblock",
+        help: "Places one block",
+        args: const { &[
+            Arg::required("x", Coord),
+            Arg::required("y", Coord),
+            Arg::required("z", Coord),
+            Arg::required("block", Block),
+        ] },
+        run: Arc::new(setblock),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "fill",
+        help: "Fills a box between two corners",
+        args: const { &[
+            Arg::required("x1", Coord),
+            Arg::required("y1", Coord),
+            Arg::required("z1", Coord),
+            Arg::required("x2", Coord),
+            Arg::required("y2", Coord),
+            Arg::required("z2", Coord),
+            Arg::required("block", Block),
+        ] },
+        run: Arc::new(fill),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "time",
+        help: "Shows how long the game has run, or changes how fast time passes",
+        args: const { &[Arg::optional("speed", Number)] },
+        run: Arc::new(time),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "seed",
+        help: "Shows the world seed",
+        args: &[],
+        run: Arc::new(seed),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "regen",
+        help: "Throws away a chunk and generates it again, defaults to the one you're in",
+        args: const { &[
+            Arg::required("what", Literal(&["chunk"])),
+            Arg::optional("x", Int),
+            Arg::optional("y", Int),
+            Arg::optional("z", Int),
+        ] },
+        run: Arc::new(regen),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "gamemode",
+        help: "Shows or changes your game mode",
+        args: const { &[Arg::optional("mode", ArgKind::GameMode)] },
+        run: Arc::new(gamemode),
+    })
+    .register_console_command(ConsoleCommand {
+        name: "render_distance",
+        help: "Shows or changes how many chunks are loaded around you",
+        args: const { &[
+            Arg::optional("horizontal", Int),
+            Arg::optional("vertical", Int),
+        ] },
+        run: Arc::new(render_distance),
+    });
+}
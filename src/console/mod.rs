@@ -0,0 +1,434 @@
+use std::sync::Arc;
+
+use bevy::math::f64::DVec3;
+use bevy::prelude::*;
+use bevy_egui::egui::text::{CCursor, CCursorRange};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::player::gamemode::GameMode;
+use crate::settings::input::{Action, ActionInput};
+use crate::world::universe::Universe;
+
+pub mod commands;
+
+const MAX_LOG_LINES: usize = 500;
+const MAX_HISTORY: usize = 100;
+
+// what an argument is, for usage text and tab completion
+#[derive(Clone, Copy)]
+pub enum ArgKind {
+    // a number, or ~ / ~n for relative to the player
+    Coord,
+    Int,
+    // any number, fractions allowed
+    Number,
+    Block,
+    GameMode,
+    // the name of another console command
+    Command,
+    Literal(&'static [&'static str]),
+    // anything at all, no completions
+    Text,
+}
+
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+impl Arg {
+    pub const fn required(name: &'static str, kind: ArgKind) -> Self {
+        Arg { name, kind, optional: false }
+    }
+
+    pub const fn optional(name: &'static str, kind: ArgKind) -> Self {
+        Arg { name, kind, optional: true }
+    }
+}
+
+// Ok is printed normally, Err in red
+pub type CommandResult = Result<String, String>;
+
+// the builtins are plain functions, commands from mods capture which script function to call
+pub type CommandFn = Arc<dyn Fn(&mut World, &[&str]) -> CommandResult + Send + Sync>;
+
+pub struct ConsoleCommand {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub args: &'static [Arg],
+    // only gets called with an argument count that fits args
+    pub run: CommandFn,
+}
+
+impl ConsoleCommand {
+    pub fn usage(&self) -> String {
+        let mut out = String::from(self.name);
+        for arg in self.args {
+            if arg.optional {
+                out += &format!(" [{}]", arg.name);
+            } else {
+                out += &format!(" <{}>", arg.name);
+            }
+        }
+        out
+    }
+
+    fn accepts(&self, count: usize) -> bool {
+        let required = self.args.iter().filter(|a| !a.optional).count();
+        count >= required && count <= self.args.len()
+    }
+
+    // literals are the only kind that can be checked before running, everything else gets parsed by the command
+    fn check_literals(&self, values: &[&str]) -> Result<(), String> {
+        for (arg, value) in self.args.iter().zip(values) {
+            if let ArgKind::Literal(options) = arg.kind {
+                if !options.contains(value) {
+                    return Err(format!("{} should be {}, not \"{}\"", arg.name, options.join(" or "), value));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(Vec<ConsoleCommand>);
+
+impl ConsoleCommands {
+    // a later command with the same name replaces the earlier one
+    pub fn register(&mut self, command: ConsoleCommand) {
+        self.0.retain(|c| c.name != command.name);
+        self.0.push(command);
+        self.0.sort_by_key(|c| c.name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.0.iter().find(|c| c.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ConsoleCommand> {
+        self.0.iter()
+    }
+}
+
+pub trait RegisterConsoleCommand {
+    fn register_console_command(&mut self, command: ConsoleCommand) -> &mut Self;
+}
+
+impl RegisterConsoleCommand for App {
+    fn register_console_command(&mut self, command: ConsoleCommand) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        self.world_mut().resource_mut::<ConsoleCommands>().register(command);
+        self
+    }
+}
+
+// ARGUMENT PARSING
+
+pub fn parse_int(arg: &str, name: &str) -> Result<i32, String> {
+    arg.parse().map_err(|_| format!("{} should be a whole number, not \"{}\"", name, arg))
+}
+
+// "~" is relative_to itself, "~5" is 5 more than it
+pub fn parse_coord(arg: &str, relative_to: f64, name: &str) -> Result<f64, String> {
+    let (base, number) = match arg.strip_prefix('~') {
+        Some("") => return Ok(relative_to),
+        Some(rest) => (relative_to, rest),
+        None => (0.0, arg),
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(base + n),
+        _ => Err(format!("{} should be a number or ~, not \"{}\"", name, arg)),
+    }
+}
+
+pub fn parse_position(args: &[&str], relative_to: DVec3) -> Result<DVec3, String> {
+    Ok(DVec3::new(
+        parse_coord(args[0], relative_to.x, "x")?,
+        parse_coord(args[1], relative_to.y, "y")?,
+        parse_coord(args[2], relative_to.z, "z")?,
+    ))
+}
+
+pub fn parse_block_position(args: &[&str], relative_to: DVec3) -> Result<IVec3, String> {
+    Ok(parse_position(args, relative_to)?.floor().as_ivec3())
+}
+
+// CONSOLE STATE
+
+struct LogLine {
+    text: String,
+    error: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct ConsoleState {
+    pub open: bool,
+    input: String,
+    log: Vec<LogLine>,
+    history: Vec<String>,
+    // where we are when going back through history with the arrow keys, None when not
+    history_index: Option<usize>,
+    // submitted but not run yet, commands need the whole World so they run in their own system
+    pending: Vec<String>,
+    focus_input: bool,
+}
+
+impl ConsoleState {
+    pub fn print(&mut self, text: impl Into<String>) {
+        self.push(text.into(), false);
+    }
+
+    pub fn print_error(&mut self, text: impl Into<String>) {
+        self.push(text.into(), true);
+    }
+
+    fn push(&mut self, text: String, error: bool) {
+        for line in text.lines() {
+            self.log.push(LogLine { text: line.to_string(), error });
+        }
+        let extra = self.log.len().saturating_sub(MAX_LOG_LINES);
+        self.log.drain(..extra);
+    }
+
+    pub fn clear(&mut self) {
+        self.log.clear();
+    }
+
+    fn submit(&mut self) {
+        let line = self.input.trim().to_string();
+        self.input.clear();
+        self.history_index = None;
+        if line.is_empty() {
+            return;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            let extra = self.history.len().saturating_sub(MAX_HISTORY);
+            self.history.drain(..extra);
+        }
+        self.pending.push(line);
+    }
+
+    // older is true for the up arrow
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_index = match (self.history_index, older) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(i), true) => Some(i.saturating_sub(1)),
+            (Some(i), false) if i < last => Some(i + 1),
+            (Some(_), false) => None,
+        };
+        self.input = match self.history_index {
+            Some(i) => self.history[i].clone(),
+            None => String::new(),
+        };
+    }
+}
+
+// TAB COMPLETION
+
+fn candidates(kind: ArgKind, commands: &ConsoleCommands, universe: &Universe) -> Vec<String> {
+    match kind {
+        ArgKind::Coord => vec![String::from("~")],
+        ArgKind::Int | ArgKind::Number => vec![],
+        ArgKind::Block => universe.registered_blocks().into_iter().map(|(_, data)| data.name.clone()).collect(),
+        ArgKind::GameMode => GameMode::ALL.iter().map(|m| m.name().to_string()).collect(),
+        ArgKind::Command => commands.iter().map(|c| c.name.to_string()).collect(),
+        ArgKind::Literal(options) => options.iter().map(|o| o.to_string()).collect(),
+        ArgKind::Text => vec![],
+    }
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let Some(first) = words.first() else {
+        return String::new();
+    };
+    let mut len = first.len();
+    for w in &words[1..] {
+        len = len.min(first.bytes().zip(w.bytes()).take_while(|(a, b)| a == b).count());
+    }
+    first[..len].to_string()
+}
+
+// finishes the last word of the input as far as it can
+// returns every option when there's more than one, for printing
+fn complete(input: &mut String, commands: &ConsoleCommands, universe: &Universe) -> Vec<String> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let typing_new_word = input.is_empty() || input.ends_with(char::is_whitespace);
+    let (done, partial) = if typing_new_word {
+        (words.as_slice(), "")
+    } else {
+        (&words[..words.len() - 1], words[words.len() - 1])
+    };
+
+    let options: Vec<String> = match done.split_first() {
+        None => commands.iter().map(|c| c.name.to_string()).collect(),
+        Some((name, args)) => match commands.get(name).and_then(|c| c.args.get(args.len())) {
+            Some(arg) => candidates(arg.kind, commands, universe),
+            None => vec![],
+        },
+    };
+    let matching: Vec<String> = options.into_iter().filter(|o| o.starts_with(partial)).collect();
+    if matching.is_empty() {
+        return vec![];
+    }
+
+    let completed = if matching.len() == 1 {
+        format!("{} ", matching[0])
+    } else {
+        common_prefix(&matching)
+    };
+    let mut new_input = done.join(" ");
+    if !new_input.is_empty() {
+        new_input.push(' ');
+    }
+    new_input += &completed;
+    *input = new_input;
+
+    if matching.len() > 1 { matching } else { vec![] }
+}
+
+// SYSTEMS
+
+fn toggle_console(input: ActionInput, mut console: ResMut<ConsoleState>) {
+    if input.just_pressed(Action::ToggleConsole) {
+        console.open = !console.open;
+        console.focus_input = console.open;
+    }
+}
+
+fn console_window(
+    mut egui: EguiContexts,
+    mut console: ResMut<ConsoleState>,
+    commands: Res<ConsoleCommands>,
+    universe: Res<Universe>,
+) {
+    let ctx = egui.ctx_mut();
+    let input_id = egui::Id::new("console_input");
+    let mut open = console.open;
+
+    // the text box would use these keys for itself (or move focus away with tab), so take them first
+    let focused = ctx.memory(|m| m.has_focus(input_id));
+    let take = |key| ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+    let (tab, up, down, escape) = if focused {
+        (take(egui::Key::Tab), take(egui::Key::ArrowUp), take(egui::Key::ArrowDown), take(egui::Key::Escape))
+    } else {
+        (false, false, false, false)
+    };
+    if escape {
+        open = false;
+    }
+    let mut move_cursor_to_end = false;
+    if up || down {
+        console.browse_history(up);
+        move_cursor_to_end = true;
+    }
+    if tab {
+        let mut text = std::mem::take(&mut console.input);
+        let options = complete(&mut text, &commands, &universe);
+        console.input = text;
+        if !options.is_empty() {
+            console.print(options.join("  "));
+        }
+        move_cursor_to_end = true;
+    }
+
+    egui::Window::new("Console")
+        .open(&mut open)
+        .default_size([600.0, 300.0])
+        .show(ctx, |ui| {
+            let log_height = ui.available_height() - 30.0;
+            egui::ScrollArea::vertical()
+                .max_height(log_height.max(50.0))
+                .stick_to_bottom(true)
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    for line in &console.log {
+                        let color = if line.error { egui::Color32::LIGHT_RED } else { ui.visuals().text_color() };
+                        ui.label(egui::RichText::new(&line.text).monospace().color(color));
+                    }
+                });
+
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut console.input)
+                    .id(input_id)
+                    .font(egui::TextStyle::Monospace)
+                    .hint_text("type help for a list of commands")
+                    .desired_width(f32::INFINITY),
+            );
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                console.submit();
+                console.focus_input = true;
+            }
+            if console.focus_input {
+                response.request_focus();
+                console.focus_input = false;
+            }
+        });
+
+    if move_cursor_to_end {
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, input_id) {
+            let end = CCursor::new(console.input.chars().count());
+            state.cursor.set_char_range(Some(CCursorRange::one(end)));
+            state.store(ctx, input_id);
+        }
+    }
+    console.open = open;
+}
+
+pub fn run_command(world: &mut World, line: &str) -> CommandResult {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((name, args)) = words.split_first() else {
+        return Ok(String::new());
+    };
+
+    // the run function is cloned out so the registry can be let go of before running
+    let (run, usage) = {
+        let commands = world.resource::<ConsoleCommands>();
+        let Some(command) = commands.get(name) else {
+            return Err(format!("Unknown command \"{}\", try help", name));
+        };
+        if !command.accepts(args.len()) {
+            return Err(format!("Usage: {}", command.usage()));
+        }
+        if let Err(e) = command.check_literals(args) {
+            return Err(format!("{}\nUsage: {}", e, command.usage()));
+        }
+        (command.run.clone(), command.usage())
+    };
+    run(world, args).map_err(|e| format!("{}\nUsage: {}", e, usage))
+}
+
+fn run_pending_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<ConsoleState>().pending);
+    for line in pending {
+        world.resource_mut::<ConsoleState>().print(format!("> {}", line));
+        let result = run_command(world, &line);
+        let mut console = world.resource_mut::<ConsoleState>();
+        match result {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => console.print(text),
+            Err(text) => console.print_error(text),
+        }
+    }
+}
+
+pub struct ConsolePlugin;
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleState>()
+            .init_resource::<ConsoleCommands>()
+            .add_systems(Update, (
+                toggle_console,
+                console_window.run_if(|c: Res<ConsoleState>| {c.open}),
+                run_pending_commands,
+            ).chain());
+        commands::register_builtin_commands(app);
+    }
+}
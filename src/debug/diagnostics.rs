@@ -0,0 +1,152 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin, RegisterDiagnostic};
+use bevy::prelude::*;
+
+use crate::world::loading::ChunkPipelineStats;
+use crate::world::universe::{DbTimingTotals, Universe};
+
+pub const CHUNKS_GENERATED: DiagnosticPath = DiagnosticPath::const_new("chunks/generated_per_second");
+pub const CHUNKS_MESHED: DiagnosticPath = DiagnosticPath::const_new("chunks/meshed_per_second");
+pub const GENERATE_TIME: DiagnosticPath = DiagnosticPath::const_new("chunks/generate_time");
+pub const BAKE_TIME: DiagnosticPath = DiagnosticPath::const_new("chunks/bake_time");
+pub const VERTICES_UPLOADED: DiagnosticPath = DiagnosticPath::const_new("chunks/vertices_uploaded");
+pub const DB_READ_LATENCY: DiagnosticPath = DiagnosticPath::const_new("db/read_latency");
+pub const DB_WRITE_LATENCY: DiagnosticPath = DiagnosticPath::const_new("db/write_latency");
+
+// in the order they're shown in the debug menu and written to the csv
+pub const CHUNK_DIAGNOSTICS: [(&DiagnosticPath, &str); 7] = [
+    (&CHUNKS_GENERATED, "Chunks generated"),
+    (&CHUNKS_MESHED, "Chunks meshed"),
+    (&GENERATE_TIME, "Mean generation time"),
+    (&BAKE_TIME, "Mean bake time"),
+    (&VERTICES_UPLOADED, "Vertices uploaded"),
+    (&DB_READ_LATENCY, "DB read latency"),
+    (&DB_WRITE_LATENCY, "DB write latency"),
+];
+
+// set this to a file path to get a row of metrics every second, for comparing builds
+const CSV_ENV_VAR: &str = "DIRLAKU_METRICS_CSV";
+
+// the counters from last frame, everything per second or per frame is the difference from these
+#[derive(Default)]
+struct LastTotals {
+    generated: u64,
+    baked: u64,
+    vertices: u64,
+    db: DbTimingTotals,
+}
+
+fn nanos_to_micros(nanos: u64, count: u64) -> f64 {
+    nanos as f64 / count as f64 / 1000.0
+}
+
+fn measure_chunk_pipeline(
+    mut diagnostics: Diagnostics,
+    stats: Res<ChunkPipelineStats>,
+    universe: Res<Universe>,
+    time: Res<Time<Real>>,
+    mut last: Local<LastTotals>,
+) {
+    let db = universe.db_timing_totals();
+    let dt = time.delta_seconds_f64();
+
+    if dt > 0.0 {
+        diagnostics.add_measurement(&CHUNKS_GENERATED, || (stats.generated - last.generated) as f64 / dt);
+        diagnostics.add_measurement(&CHUNKS_MESHED, || (stats.baked - last.baked) as f64 / dt);
+    }
+    diagnostics.add_measurement(&VERTICES_UPLOADED, || (stats.vertices_uploaded - last.vertices) as f64);
+
+    // the stats already keep a window of recent timings, no point averaging an average
+    if let Some(t) = ChunkPipelineStats::average(&stats.generate_times) {
+        diagnostics.add_measurement(&GENERATE_TIME, || t.as_secs_f64() * 1000.0);
+    }
+    if let Some(t) = ChunkPipelineStats::average(&stats.bake_times) {
+        diagnostics.add_measurement(&BAKE_TIME, || t.as_secs_f64() * 1000.0);
+    }
+
+    // frames without any reads or writes don't say anything about latency
+    let reads = db.reads - last.db.reads;
+    if reads > 0 {
+        diagnostics.add_measurement(&DB_READ_LATENCY, || nanos_to_micros(db.read_nanos - last.db.read_nanos, reads));
+    }
+    let writes = db.writes - last.db.writes;
+    if writes > 0 {
+        diagnostics.add_measurement(&DB_WRITE_LATENCY, || nanos_to_micros(db.write_nanos - last.db.write_nanos, writes));
+    }
+
+    *last = LastTotals {
+        generated: stats.generated,
+        baked: stats.baked,
+        vertices: stats.vertices_uploaded,
+        db,
+    };
+}
+
+#[derive(Resource)]
+struct MetricsCsv {
+    out: BufWriter<File>,
+    timer: Timer,
+}
+
+impl MetricsCsv {
+    fn create(path: PathBuf) -> std::io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let mut header = vec![String::from("seconds"), String::from("fps")];
+        header.extend(CHUNK_DIAGNOSTICS.iter().map(|(path, _)| path.as_str().replace('/', "_")));
+        writeln!(out, "{}", header.join(","))?;
+        Ok(Self { out, timer: Timer::from_seconds(1.0, TimerMode::Repeating) })
+    }
+}
+
+fn write_metrics_csv(
+    mut csv: ResMut<MetricsCsv>,
+    diagnostics: Res<DiagnosticsStore>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+) {
+    if !csv.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let value = |path: &DiagnosticPath| match diagnostics.get(path).and_then(|d| d.smoothed()) {
+        Some(v) => format!("{:.3}", v),
+        None => String::new(),
+    };
+    let mut row = vec![format!("{:.1}", time.elapsed_seconds_f64()), value(&FrameTimeDiagnosticsPlugin::FPS)];
+    row.extend(CHUNK_DIAGNOSTICS.iter().map(|(path, _)| value(path)));
+
+    let result = writeln!(csv.out, "{}", row.join(",")).and_then(|_| csv.out.flush());
+    if let Err(e) = result {
+        warn!("Couldn't write metrics csv, not logging any more: {}", e);
+        commands.remove_resource::<MetricsCsv>();
+    }
+}
+
+pub struct ChunkDiagnosticsPlugin;
+impl Plugin for ChunkDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(CHUNKS_GENERATED).with_suffix(" chunks/s"))
+            .register_diagnostic(Diagnostic::new(CHUNKS_MESHED).with_suffix(" chunks/s"))
+            .register_diagnostic(Diagnostic::new(GENERATE_TIME).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(BAKE_TIME).with_suffix(" ms"))
+            .register_diagnostic(Diagnostic::new(VERTICES_UPLOADED).with_suffix(" vertices/frame"))
+            .register_diagnostic(Diagnostic::new(DB_READ_LATENCY).with_suffix(" µs"))
+            .register_diagnostic(Diagnostic::new(DB_WRITE_LATENCY).with_suffix(" µs"))
+            .add_systems(Last, measure_chunk_pipeline);
+
+        if let Some(path) = std::env::var_os(CSV_ENV_VAR) {
+            let path = PathBuf::from(path);
+            match MetricsCsv::create(path.clone()) {
+                Ok(csv) => {
+                    info!("Logging metrics to {}", path.display());
+                    app.insert_resource(csv)
+                        .add_systems(Last, write_metrics_csv.after(measure_chunk_pipeline).run_if(resource_exists::<MetricsCsv>));
+                }
+                Err(e) => warn!("Couldn't create metrics csv at {}: {}", path.display(), e),
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use crate::chunk::chunk::{CHUNK_SIZE, CHUNK_SIZE_I32};
-use crate::player::PLAYER_REACH;
+use crate::player::{RenderOrigin, PLAYER_REACH};
+use crate::position::universe_location::UniverseLocation;
+use crate::position::raycast::RaycastHit;
 use crate::{player::ThisPlayer, settings::Settings};
+use crate::settings::input::{Action, ActionInput};
 use crate::position::universe_transform::UniverseTransform;
 use crate::world::universe::Universe;
 use bevy::{
@@ -12,6 +15,11 @@ use bevy_egui::egui::{Color32, TextFormat};
 use bevy_egui::{egui, EguiContexts};
 use bevy_math::{CompassOctant, DVec3};
 
+mod diagnostics;
+mod pipeline;
+use diagnostics::{ChunkDiagnosticsPlugin, CHUNK_DIAGNOSTICS};
+use pipeline::chunk_pipeline_window;
+
 pub fn display_debug_menu(
     mut egui: EguiContexts,
     mut ds: ResMut<DebugInfo>,
@@ -24,6 +32,7 @@ pub fn display_debug_menu(
         ui.checkbox(&mut ds.show_game_info, "Show Game Info");
         ui.checkbox(&mut ds.draw_chunk_borders, "Draw Chunk Borders");
         ui.checkbox(&mut ds.draw_viewed_blocks, "Draw Blocks in Line of Sight");
+        ui.checkbox(&mut ds.show_pipeline_info, "Show Chunk Pipeline");
         ui.checkbox(&mut ds.enable_debug_keyinds, "Enable Debug Keybinds");
 
         if ds.show_perf_info {
@@ -35,6 +44,14 @@ pub fn display_debug_menu(
             ui.label(format!(
                 "Entities: {:.0}", diagnostics.get(&EntityCountDiagnosticsPlugin::ENTITY_COUNT).unwrap().smoothed().unwrap_or_default()
             ));
+
+            for (path, name) in CHUNK_DIAGNOSTICS {
+                let diagnostic = diagnostics.get(path).unwrap();
+                match diagnostic.smoothed() {
+                    Some(v) => ui.label(format!("{}: {:.2}{}", name, v, diagnostic.suffix)),
+                    None => ui.label(format!("{}: -", name)),
+                };
+            }
         }
 
         if ds.show_game_info {
@@ -83,11 +100,9 @@ pub fn display_debug_menu(
 fn render_chunk_borders(
     mut gizmos: Gizmos,
     settings: Res<Settings>,
-    player_query: Query<&UniverseTransform, With<ThisPlayer>>
+    origin: Res<RenderOrigin>
 ) {
-    let player_utrans = player_query.single();
-
-    let chunk_corner = -player_utrans.get_within_chunk_position();
+    let chunk_corner = -UniverseLocation::from_dim_xyz(0, origin.0).get_within_chunk_position();
     gizmos.grid_3d(
         chunk_corner,
         Quat::IDENTITY,
@@ -130,41 +145,73 @@ fn render_chunk_borders(
     })
 }
 
+fn face_name(normal: IVec3) -> &'static str {
+    match normal.to_array() {
+        [1, 0, 0] => "+X",
+        [-1, 0, 0] => "-X",
+        [0, 1, 0] => "+Y",
+        [0, -1, 0] => "-Y",
+        [0, 0, 1] => "+Z",
+        [0, 0, -1] => "-Z",
+        _ => "start",
+    }
+}
+
 pub fn draw_int_raycast(
     mut gizmos: Gizmos,
     mut egui: EguiContexts,
-    player: Query<&UniverseTransform, With<ThisPlayer>>
-    
+    player: Query<&UniverseTransform, With<ThisPlayer>>,
+    origin: Res<RenderOrigin>
 ) {
     let player_trans = player.single();
-    let rc = player_trans.integer_raycast(PLAYER_REACH);
+    let rc: Vec<RaycastHit> = player_trans.voxel_raycast(PLAYER_REACH).collect();
+    // the old raycast, blocks where the two disagree are drawn in red
+    let old_rc: Vec<IVec3> = player_trans.integer_raycast(PLAYER_REACH)
+        .iter()
+        .map(|l| l.position.as_ivec3())
+        .collect();
     
-    for block_loc in rc.clone() {
+    for (i, hit) in rc.iter().enumerate() {
+        let agrees = old_rc.get(i) == Some(&hit.block);
         gizmos.cuboid(
-            Transform::from_translation((block_loc.position - player_trans.loc.position + (0.5 * DVec3::ONE)).as_vec3()), 
-            bevy::color::palettes::css::BLUE
+            Transform::from_translation((hit.block.as_dvec3() - origin.0 + (0.5 * DVec3::ONE)).as_vec3()), 
+            if agrees {bevy::color::palettes::css::BLUE} else {bevy::color::palettes::css::RED}
+        );
+        gizmos.sphere(
+            (hit.point - origin.0).as_vec3(),
+            Quat::IDENTITY,
+            0.03,
+            bevy::color::palettes::css::ORANGE
         );
     }
 
     let num = rc.len();
-    let mut prev = rc[0].position;
+    let mut prev = rc[0].block;
     egui::Window::new("Raycasted Blocks").show(egui.ctx_mut(), |ui| {
         ui.heading(format!("{} Blocks Hit", num));
-        for b in rc {
-            let delta = b.position - prev;
+        if old_rc.len() != num || rc.iter().zip(&old_rc).any(|(h, o)| h.block != *o) {
+            ui.colored_label(Color32::RED, "Differs from integer_raycast");
+        }
+        for hit in &rc {
+            let delta = hit.block - prev;
             let mut job = LayoutJob::default();
             for i in [0,1,2] {
                 job.append(
-                    &format!("{:.0} ", b.position[i]),
+                    &format!("{} ", hit.block[i]),
                     0.0,
                     TextFormat {
-                        color: if delta[i] > 0.0 {Color32::GREEN} else if delta[i] < 0.0 {Color32::RED} else {Color32::WHITE}, 
+                        color: if delta[i] > 0 {Color32::GREEN} else if delta[i] < 0 {Color32::RED} else {Color32::WHITE}, 
                         ..default()
                     }
                 )
             }
+            job.append(
+                &format!("via {} at {:.2}", face_name(hit.normal), hit.distance),
+                0.0,
+                TextFormat::default()
+            );
             ui.label(job);
-            prev = b.position;
+            prev = hit.block;
         }
     });
 
@@ -173,40 +220,46 @@ pub fn draw_int_raycast(
 
 pub fn debug_keybinds(
     mut egui: EguiContexts,
-    keys: Res<ButtonInput<KeyCode>>,
+    input: ActionInput,
+    settings: Res<Settings>,
     mut player_query: Query<&mut UniverseTransform, With<ThisPlayer>>,
 ) {
     let mut worldpos = player_query.single_mut();
-    if keys.pressed(KeyCode::ControlLeft) || keys.pressed(KeyCode::ControlRight) {
-        if keys.just_pressed(KeyCode::KeyQ) {
+    if input.pressed(Action::DebugModifier) {
+        if input.just_pressed(Action::DebugLookAhead) {
             worldpos.pitch = 0.0;
         }
-        if keys.just_pressed(KeyCode::KeyX) {
+        if input.just_pressed(Action::DebugResetYaw) {
             worldpos.yaw = 0.0;
         }
-        if keys.just_pressed(KeyCode::KeyZ) {
+        if input.just_pressed(Action::DebugRotateLeft) {
             worldpos.add_yaw(-std::f64::consts::FRAC_PI_2)
         }
-        if keys.just_pressed(KeyCode::KeyC) {
+        if input.just_pressed(Action::DebugRotateRight) {
             worldpos.add_yaw(std::f64::consts::FRAC_PI_2)
         }
-        if keys.just_pressed(KeyCode::KeyF) {
+        if input.just_pressed(Action::DebugRoundPosition) {
             worldpos.loc.position = worldpos.loc.position.floor();
         }
     }
 
     egui::Window::new("Debug Keybindings").show(egui.ctx_mut(), |ui| {
+        let bound = |action| {
+            let b: Vec<String> = settings.bindings.bindings(action).iter().map(|b| b.to_string()).collect();
+            b.join("/")
+        };
+        let modifier = bound(Action::DebugModifier);
         ui.heading("Position Controls");
-        ui.label("Ctrl+Q: Look straight ahead");
-        ui.label("Ctrl+X: Set yaw to 0°");
-        ui.label("Ctrl+Z: Rotate 90° CCW");
-        ui.label("Ctrl+C: Rotate 90° CW");
-        ui.label("Ctrl+F: Round position to nearest integer");
+        ui.label(format!("{}+{}: Look straight ahead", modifier, bound(Action::DebugLookAhead)));
+        ui.label(format!("{}+{}: Set yaw to 0°", modifier, bound(Action::DebugResetYaw)));
+        ui.label(format!("{}+{}: Rotate 90° CCW", modifier, bound(Action::DebugRotateLeft)));
+        ui.label(format!("{}+{}: Rotate 90° CW", modifier, bound(Action::DebugRotateRight)));
+        ui.label(format!("{}+{}: Round position to nearest integer", modifier, bound(Action::DebugRoundPosition)));
     });
 }
 
-pub fn toggle_debug_info(keys: Res<ButtonInput<KeyCode>>, mut ui_state: ResMut<DebugInfo>) {
-    if keys.just_pressed(KeyCode::F3) {
+pub fn toggle_debug_info(input: ActionInput, mut ui_state: ResMut<DebugInfo>) {
+    if input.just_pressed(Action::ToggleDebugInfo) {
             ui_state.show_all_info = !ui_state.show_all_info;
     }
 }
@@ -218,7 +271,9 @@ pub struct DebugInfo {
     pub show_game_info : bool,
     pub draw_chunk_borders: bool, 
     pub draw_viewed_blocks: bool,
-    pub enable_debug_keyinds: bool
+    pub enable_debug_keyinds: bool,
+    pub show_pipeline_info: bool,
+    pub draw_chunk_minimap: bool
 }
 
 const DEFAULT_DEBUG_STATE : DebugInfo = DebugInfo {
@@ -227,20 +282,23 @@ const DEFAULT_DEBUG_STATE : DebugInfo = DebugInfo {
     show_game_info: true,
     draw_chunk_borders: false,
     draw_viewed_blocks: false, 
-    enable_debug_keyinds: false
+    enable_debug_keyinds: false,
+    show_pipeline_info: false,
+    draw_chunk_minimap: false
 };
 
 pub struct DebugTextPlugin;
 impl Plugin for DebugTextPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin, RenderDiagnosticsPlugin))
+        app.add_plugins((FrameTimeDiagnosticsPlugin, EntityCountDiagnosticsPlugin, RenderDiagnosticsPlugin, ChunkDiagnosticsPlugin))
             .insert_resource(DEFAULT_DEBUG_STATE)
             .add_systems(Update, toggle_debug_info)
             .add_systems(Update, (
                 display_debug_menu, // runs only if the master checkbox is toggled
                 render_chunk_borders.run_if(|ds : Res<DebugInfo> | {ds.draw_chunk_borders}),
                 draw_int_raycast.run_if(|ds : Res<DebugInfo> | {ds.draw_viewed_blocks}),
-                debug_keybinds.run_if(|ds : Res<DebugInfo> | {ds.enable_debug_keyinds})
+                debug_keybinds.run_if(|ds : Res<DebugInfo> | {ds.enable_debug_keyinds}),
+                chunk_pipeline_window.run_if(|ds : Res<DebugInfo> | {ds.show_pipeline_info})
             ).run_if(|ds : Res<DebugInfo> | {ds.show_all_info}));
     }
 }
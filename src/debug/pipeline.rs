@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_egui::egui::{Color32, Sense, Stroke};
+use bevy_egui::{egui, EguiContexts};
+
+use super::DebugInfo;
+use crate::player::ThisPlayer;
+use crate::position::universe_transform::UniverseTransform;
+use crate::settings::Settings;
+use crate::world::loading::{ChunkPipelineStats, ChunkPosition, ChunkRemeshTask, ChunkTriangles, GenerateChunkTask};
+use crate::world::universe::Universe;
+
+// where a loaded chunk is in the pipeline, from least to most done
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ChunkState {
+    // has a task that the task pool hasn't gotten to yet
+    Queued,
+    Generating,
+    Meshing,
+    Ready,
+}
+
+impl ChunkState {
+    const ALL: [ChunkState; 4] = [ChunkState::Queued, ChunkState::Generating, ChunkState::Meshing, ChunkState::Ready];
+
+    fn of(generating: Option<&GenerateChunkTask>, meshing: Option<&ChunkRemeshTask>) -> Self {
+        match (generating, meshing) {
+            (Some(task), _) if task.1.get() => ChunkState::Generating,
+            (Some(_), _) => ChunkState::Queued,
+            (None, Some(task)) if task.1.get() => ChunkState::Meshing,
+            (None, Some(_)) => ChunkState::Queued,
+            (None, None) => ChunkState::Ready,
+        }
+    }
+
+    fn color(&self) -> Color32 {
+        match self {
+            ChunkState::Queued => Color32::from_rgb(90, 90, 90),
+            ChunkState::Generating => Color32::from_rgb(220, 80, 60),
+            ChunkState::Meshing => Color32::from_rgb(230, 190, 50),
+            ChunkState::Ready => Color32::from_rgb(70, 170, 80),
+        }
+    }
+}
+
+fn format_time(time: Option<std::time::Duration>) -> String {
+    match time {
+        Some(t) => format!("{:.2} ms", t.as_secs_f64() * 1000.0),
+        None => String::from("-"),
+    }
+}
+
+const MINIMAP_CELL: f32 = 8.0;
+
+pub fn chunk_pipeline_window(
+    mut egui: EguiContexts,
+    mut ds: ResMut<DebugInfo>,
+    stats: Res<ChunkPipelineStats>,
+    universe: Res<Universe>,
+    settings: Res<Settings>,
+    player_query: Query<&UniverseTransform, With<ThisPlayer>>,
+    chunks: Query<(&ChunkPosition, Option<&GenerateChunkTask>, Option<&ChunkRemeshTask>, Option<&ChunkTriangles>)>,
+) {
+    let player_chunk = player_query.single().get_chunk_position();
+
+    let mut counts: HashMap<ChunkState, usize> = HashMap::new();
+    let mut generate_tasks = 0;
+    let mut remesh_tasks = 0;
+    let mut total_triangles: u64 = 0;
+    // every loaded chunk in each x z column, for the minimap
+    let mut columns: HashMap<(i32, i32), Vec<(i32, ChunkState, u32)>> = HashMap::new();
+    let mut heaviest: Vec<(IVec3, u32)> = Vec::new();
+
+    for (pos, generating, meshing, triangles) in &chunks {
+        let state = ChunkState::of(generating, meshing);
+        *counts.entry(state).or_default() += 1;
+        generate_tasks += generating.is_some() as usize;
+        remesh_tasks += meshing.is_some() as usize;
+
+        let triangles = triangles.map(|t| t.0).unwrap_or(0);
+        total_triangles += triangles as u64;
+        heaviest.push((pos.0, triangles));
+        columns.entry((pos.0.x, pos.0.z)).or_default().push((pos.0.y, state, triangles));
+    }
+    heaviest.sort_by(|a, b| b.1.cmp(&a.1));
+    heaviest.truncate(5);
+
+    egui::Window::new("Chunk Pipeline").show(egui.ctx_mut(), |ui| {
+        ui.heading("Chunks");
+        for state in ChunkState::ALL {
+            ui.colored_label(state.color(), format!("{:?}: {}", state, counts.get(&state).copied().unwrap_or(0)));
+        }
+        ui.label(format!("In flight: {} generate tasks, {} remesh tasks", generate_tasks, remesh_tasks));
+
+        ui.heading("Timings");
+        ui.label(format!(
+            "Average generation: {} ({} generated)",
+            format_time(ChunkPipelineStats::average(&stats.generate_times)), stats.generated
+        ));
+        ui.label(format!(
+            "Average bake: {} ({} baked)",
+            format_time(ChunkPipelineStats::average(&stats.bake_times)), stats.baked
+        ));
+
+        ui.heading("Meshes");
+        ui.label(format!("Triangles: {}", total_triangles));
+        for (pos, triangles) in &heaviest {
+            ui.label(format!("  {} {} {}: {} triangles", pos.x, pos.y, pos.z, triangles));
+        }
+
+        ui.heading("Storage");
+        match universe.size_on_disk() {
+            Some(bytes) => ui.label(format!("World database: {:.1} MiB", bytes as f64 / (1024.0 * 1024.0))),
+            None => ui.label("World database: unknown size"),
+        };
+
+        ui.checkbox(&mut ds.draw_chunk_minimap, "Show Minimap");
+        if !ds.draw_chunk_minimap {
+            return;
+        }
+
+        // top down, +X (north) is up and +Z (east) is right, like the facing readout
+        // each column shows its least done chunk
+        let radius = settings.horizontal_render_distance as i32 + 1;
+        let cells = (radius * 2 + 1) as f32;
+        let (response, painter) = ui.allocate_painter(egui::Vec2::splat(cells * MINIMAP_CELL), Sense::hover());
+        let origin = response.rect.min;
+        let cell_rect = |row: i32, col: i32| {
+            let min = origin + egui::vec2(col as f32, row as f32) * MINIMAP_CELL;
+            egui::Rect::from_min_size(min, egui::Vec2::splat(MINIMAP_CELL - 1.0))
+        };
+
+        for row in 0..=radius * 2 {
+            for col in 0..=radius * 2 {
+                let x = player_chunk.x + radius - row;
+                let z = player_chunk.z - radius + col;
+                let color = match columns.get(&(x, z)) {
+                    Some(column) => column.iter().map(|c| c.1).min().unwrap().color(),
+                    None => Color32::from_black_alpha(120),
+                };
+                painter.rect_filled(cell_rect(row, col), 0.0, color);
+            }
+        }
+        painter.rect_stroke(cell_rect(radius, radius), 0.0, Stroke::new(1.5, Color32::WHITE));
+
+        let Some(hover) = response.hover_pos() else {
+            return;
+        };
+        let cell = ((hover - origin) / MINIMAP_CELL).floor();
+        let (x, z) = (player_chunk.x + radius - cell.y as i32, player_chunk.z - radius + cell.x as i32);
+        let mut text = format!("Column X {} Z {}", x, z);
+        if let Some(column) = columns.get(&(x, z)) {
+            let mut column = column.clone();
+            column.sort_by_key(|c| c.0);
+            for (y, state, triangles) in column {
+                text += &format!("\nY {}: {:?}, {} triangles", y, state, triangles);
+            }
+        }
+        response.on_hover_text(text);
+    });
+}
@@ -0,0 +1,227 @@
+use crate::chunk::chunk::BlockId;
+use crate::player::{RenderOrigin, ThisPlayer};
+use crate::position::universe_transform::UniverseTransform;
+use crate::settings::input::{Action, ActionInput};
+use crate::world::edits::EditCause;
+use crate::world::region::{Clipboard, Region};
+use crate::world::universe::Universe;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+// how far away corners can be selected from
+pub const EDIT_REACH: f64 = 64.0;
+
+#[derive(Resource)]
+pub struct WorldEditState {
+    pub show_window: bool,
+    pub pos1: Option<IVec3>,
+    pub pos2: Option<IVec3>,
+    pub clipboard: Option<Clipboard>,
+    pub paste_skip_air: bool,
+
+    // text boxes in the menu
+    pub fill_block: String,
+    pub replace_from: String,
+    pub replace_to: String,
+    pub status: String,
+}
+
+impl WorldEditState {
+    pub fn selection(&self) -> Option<Region> {
+        Some(Region::from_corners(self.pos1?, self.pos2?))
+    }
+}
+
+impl Default for WorldEditState {
+    fn default() -> Self {
+        WorldEditState {
+            show_window: false,
+            pos1: None,
+            pos2: None,
+            clipboard: None,
+            paste_skip_air: true,
+            fill_block: String::from("stone"),
+            replace_from: String::from("stone"),
+            replace_to: String::from("dirt"),
+            status: String::new(),
+        }
+    }
+}
+
+// the first non-air block the given transform is looking at, if any
+fn targeted_block(universe: &Universe, trans: &UniverseTransform) -> Option<IVec3> {
+    universe.raycast_block(trans, EDIT_REACH).map(|(hit, _)| hit.block)
+}
+
+fn toggle_world_edit(input: ActionInput, mut state: ResMut<WorldEditState>) {
+    if input.just_pressed(Action::ToggleWorldEdit) {
+        state.show_window = !state.show_window;
+    }
+}
+
+fn select_corners(
+    input: ActionInput,
+    mut state: ResMut<WorldEditState>,
+    universe: Res<Universe>,
+    player: Query<&UniverseTransform, With<ThisPlayer>>,
+) {
+    let set_first = input.just_pressed(Action::SetEditCorner1);
+    let set_second = input.just_pressed(Action::SetEditCorner2);
+    if !set_first && !set_second {
+        return;
+    }
+
+    let Some(target) = targeted_block(&universe, player.single()) else {
+        state.status = String::from("No block in range to select");
+        return;
+    };
+
+    if set_first {
+        state.pos1 = Some(target);
+    } else {
+        state.pos2 = Some(target);
+    }
+}
+
+fn draw_selection(
+    mut gizmos: Gizmos,
+    state: Res<WorldEditState>,
+    origin: Res<RenderOrigin>,
+) {
+    let Some(region) = state.selection() else {
+        return;
+    };
+    let origin = origin.0;
+    let size = region.size().as_dvec3();
+    let center = region.min.as_dvec3() + 0.5 * size;
+
+    gizmos.cuboid(
+        Transform::from_translation((center - origin).as_vec3()).with_scale(size.as_vec3()),
+        bevy::color::palettes::css::YELLOW,
+    );
+}
+
+fn lookup_block(universe: &Universe, name: &str, status: &mut String) -> Option<BlockId> {
+    let id = universe.try_block_id_from_name(name.trim());
+    if id.is_none() {
+        *status = format!("Unknown block \"{}\"", name.trim());
+    }
+    id
+}
+
+fn format_corner(corner: Option<IVec3>) -> String {
+    match corner {
+        Some(p) => format!("X {} Y {} Z {}", p.x, p.y, p.z),
+        None => String::from("unset"),
+    }
+}
+
+fn world_edit_menu(
+    mut egui: EguiContexts,
+    mut state: ResMut<WorldEditState>,
+    universe: Res<Universe>,
+) {
+    let state = state.as_mut();
+
+    egui::Window::new("World Edit").show(egui.ctx_mut(), |ui| {
+        ui.label("[ / ]: Select targeted block as position 1 / 2");
+        ui.label(format!("Position 1: {}", format_corner(state.pos1)));
+        ui.label(format!("Position 2: {}", format_corner(state.pos2)));
+
+        ui.horizontal(|ui| {
+            ui.label("Block:");
+            ui.text_edit_singleline(&mut state.fill_block);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Replace");
+            ui.text_edit_singleline(&mut state.replace_from);
+            ui.label("with");
+            ui.text_edit_singleline(&mut state.replace_to);
+        });
+
+        if let Some(region) = state.selection() {
+            let size = region.size();
+            ui.heading(format!("Selection: {}x{}x{} ({} blocks)", size.x, size.y, size.z, region.volume()));
+
+            ui.horizontal(|ui| {
+                if ui.button("Fill").clicked() {
+                    if let Some(b) = lookup_block(&universe, &state.fill_block, &mut state.status) {
+                        universe.fill_region(&region, b, EditCause::Command);
+                    }
+                }
+                if ui.button("Replace").clicked() {
+                    let from = lookup_block(&universe, &state.replace_from, &mut state.status);
+                    let to = lookup_block(&universe, &state.replace_to, &mut state.status);
+                    if let (Some(from), Some(to)) = (from, to) {
+                        universe.replace_in_region(&region, from, to, EditCause::Command);
+                    }
+                }
+                if ui.button("Hollow").clicked() {
+                    if let Some(b) = lookup_block(&universe, &state.fill_block, &mut state.status) {
+                        universe.hollow_region(&region, b, EditCause::Command);
+                    }
+                }
+                if ui.button("Copy").clicked() {
+                    state.clipboard = Some(universe.copy_region(&region));
+                    state.status = format!("Copied {} blocks", region.volume());
+                }
+            });
+        }
+
+        ui.heading("Clipboard");
+        match &state.clipboard {
+            None => {
+                ui.label("Empty");
+            }
+            Some(clipboard) => {
+                let size = clipboard.size;
+                ui.label(format!("{}x{}x{}", size.x, size.y, size.z));
+
+                let mut transformed = None;
+                ui.horizontal(|ui| {
+                    if ui.button("Rotate 90°").clicked() {
+                        transformed = Some(clipboard.rotate_y(1));
+                    }
+                    if ui.button("Mirror X").clicked() {
+                        transformed = Some(clipboard.mirror(0));
+                    }
+                    if ui.button("Mirror Y").clicked() {
+                        transformed = Some(clipboard.mirror(1));
+                    }
+                    if ui.button("Mirror Z").clicked() {
+                        transformed = Some(clipboard.mirror(2));
+                    }
+                });
+
+                ui.checkbox(&mut state.paste_skip_air, "Skip air when pasting");
+                if let Some(origin) = state.pos1 {
+                    if ui.button("Paste at Position 1").clicked() {
+                        universe.paste(clipboard, origin, state.paste_skip_air, EditCause::Command);
+                    }
+                }
+
+                if transformed.is_some() {
+                    state.clipboard = transformed;
+                }
+            }
+        }
+
+        if !state.status.is_empty() {
+            ui.separator();
+            ui.label(&state.status);
+        }
+    });
+}
+
+pub struct WorldEditPlugin;
+impl Plugin for WorldEditPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WorldEditState::default())
+            .add_systems(Update, toggle_world_edit)
+            .add_systems(Update, (
+                select_corners,
+                draw_selection,
+                world_edit_menu
+            ).run_if(|s: Res<WorldEditState>| {s.show_window}));
+    }
+}
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+// what a player is allowed to do
+// saved as a number in PlayerRecord, so don't reorder these
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u32)]
+pub enum GameMode {
+    // flies, breaks instantly, places forever
+    #[default]
+    Creative = 0,
+    // walks, breaking takes time and gives drops, placing uses up the inventory
+    Survival = 1,
+    // survival, but the world can't be changed
+    Adventure = 2,
+    // flies through everything and can't touch anything
+    Spectator = 3,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [
+        GameMode::Creative,
+        GameMode::Survival,
+        GameMode::Adventure,
+        GameMode::Spectator,
+    ];
+
+    pub fn from_u32(n: u32) -> Option<Self> {
+        Self::ALL.get(n as usize).copied()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Creative => "creative",
+            Self::Survival => "survival",
+            Self::Adventure => "adventure",
+            Self::Spectator => "spectator",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+
+    // whether the player can switch between walking and flying
+    pub fn can_fly(&self) -> bool {
+        *self == Self::Creative
+    }
+
+    pub fn instant_break(&self) -> bool {
+        *self == Self::Creative
+    }
+
+    // placing doesn't use up the inventory, and picking a block gives a full stack of it
+    pub fn unlimited_blocks(&self) -> bool {
+        *self == Self::Creative
+    }
+
+    // breaking and placing
+    pub fn can_edit_blocks(&self) -> bool {
+        matches!(self, Self::Creative | Self::Survival)
+    }
+
+    // targeting blocks, picking them and using the inventory
+    pub fn can_interact(&self) -> bool {
+        *self != Self::Spectator
+    }
+
+    pub fn has_collision(&self) -> bool {
+        *self != Self::Spectator
+    }
+}
@@ -0,0 +1,293 @@
+use crate::chunk::chunk::{BlockId, AIR};
+use crate::player::gamemode::GameMode;
+use crate::player::{ThisPlayer, PLAYER_REACH};
+use crate::position::universe_transform::UniverseTransform;
+use crate::settings::input::{Action, ActionInput};
+use crate::settings::menu::not_paused;
+use crate::world::universe::{Universe, UNKNOWN_BLOCK};
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::CursorGrabMode;
+use bevy_egui::{egui, EguiContexts};
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
+
+pub const HOTBAR_SLOTS: usize = 9;
+pub const STORAGE_SLOTS: usize = 27;
+pub const MAX_STACK: u32 = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, AsBytes, FromBytes, FromZeroes)]
+#[repr(C)]
+pub struct ItemStack {
+    pub block: BlockId,
+    pub count: u32,
+}
+
+impl ItemStack {
+    pub const EMPTY: ItemStack = ItemStack { block: AIR, count: 0 };
+
+    pub fn full(block: BlockId) -> Self {
+        ItemStack { block, count: MAX_STACK }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0 || self.block == AIR
+    }
+}
+
+// plain old data so it can be saved as bytes along with the rest of the player, see PlayerRecord
+#[derive(Component, Clone, AsBytes, FromBytes, FromZeroes)]
+#[repr(C)]
+pub struct Inventory {
+    pub hotbar: [ItemStack; HOTBAR_SLOTS],
+    pub storage: [ItemStack; STORAGE_SLOTS],
+    pub selected: u32,
+    _padding: u32,
+}
+
+impl Default for Inventory {
+    fn default() -> Self {
+        Inventory::new_zeroed()
+    }
+}
+
+impl Inventory {
+    fn selected_index(&self) -> usize {
+        (self.selected as usize).min(HOTBAR_SLOTS - 1)
+    }
+
+    pub fn selected_stack(&self) -> ItemStack {
+        self.hotbar[self.selected_index()]
+    }
+
+    pub fn selected_block(&self) -> Option<BlockId> {
+        let stack = self.selected_stack();
+        (!stack.is_empty()).then_some(stack.block)
+    }
+
+    pub fn select(&mut self, slot: usize) {
+        self.selected = slot.min(HOTBAR_SLOTS - 1) as u32;
+    }
+
+    // moves the selection by some number of slots, wrapping around
+    pub fn scroll(&mut self, by: i32) {
+        let n = HOTBAR_SLOTS as i32;
+        self.selected = (self.selected as i32 + by).rem_euclid(n) as u32;
+    }
+
+    pub fn set_selected(&mut self, stack: ItemStack) {
+        let i = self.selected_index();
+        self.hotbar[i] = stack;
+    }
+
+    // removes one block from the selected stack, returning what it was
+    pub fn take_selected(&mut self) -> Option<BlockId> {
+        let i = self.selected_index();
+        let stack = &mut self.hotbar[i];
+        if stack.is_empty() {
+            return None;
+        }
+        stack.count -= 1;
+        let block = stack.block;
+        if stack.count == 0 {
+            *stack = ItemStack::EMPTY;
+        }
+        Some(block)
+    }
+
+    // adds blocks, topping up existing stacks first and then filling empty slots (hotbar first)
+    // returns how many didn't fit
+    pub fn add(&mut self, block: BlockId, mut count: u32) -> u32 {
+        for stack in self.hotbar.iter_mut().chain(self.storage.iter_mut()) {
+            if count == 0 {
+                break;
+            }
+            if !stack.is_empty() && stack.block == block && stack.count < MAX_STACK {
+                let moved = count.min(MAX_STACK - stack.count);
+                stack.count += moved;
+                count -= moved;
+            }
+        }
+        for stack in self.hotbar.iter_mut().chain(self.storage.iter_mut()) {
+            if count == 0 {
+                break;
+            }
+            if stack.is_empty() {
+                let moved = count.min(MAX_STACK);
+                *stack = ItemStack { block, count: moved };
+                count -= moved;
+            }
+        }
+        count
+    }
+
+    // selects a hotbar slot holding block, or swaps a stack of it in from storage
+    // returns false if there isn't any of it anywhere
+    pub fn pick(&mut self, block: BlockId) -> bool {
+        if let Some(slot) = self.hotbar.iter().position(|s| !s.is_empty() && s.block == block) {
+            self.select(slot);
+            return true;
+        }
+        if let Some(slot) = self.storage.iter().position(|s| !s.is_empty() && s.block == block) {
+            self.swap_with_selected(slot);
+            return true;
+        }
+        false
+    }
+
+    pub fn swap_with_selected(&mut self, storage_slot: usize) {
+        let i = self.selected_index();
+        std::mem::swap(&mut self.hotbar[i], &mut self.storage[storage_slot]);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct InventoryUiState {
+    pub show_inventory: bool,
+}
+
+fn hotbar_selection(
+    input: ActionInput,
+    mut wheel: EventReader<MouseWheel>,
+    window_query: Query<&Window>,
+    mut player: Query<&mut Inventory, With<ThisPlayer>>,
+) {
+    let mut inventory = player.single_mut();
+
+    for (slot, action) in Action::HOTBAR.into_iter().enumerate() {
+        if input.just_pressed(action) {
+            inventory.select(slot);
+        }
+    }
+
+    if input.just_pressed(Action::HotbarNext) {
+        inventory.scroll(1);
+    }
+    if input.just_pressed(Action::HotbarPrevious) {
+        inventory.scroll(-1);
+    }
+
+    // only scroll the hotbar while playing, otherwise the wheel belongs to the menus
+    let scrolled: f32 = wheel.read().map(|e| e.y).sum();
+    if window_query.single().cursor.grab_mode == CursorGrabMode::None || scrolled == 0.0 {
+        return;
+    }
+    // scrolling down moves right, like every other game
+    inventory.scroll(if scrolled < 0.0 { 1 } else { -1 });
+}
+
+fn pick_block(
+    input: ActionInput,
+    universe: Res<Universe>,
+    mut player: Query<(&UniverseTransform, &mut Inventory, &GameMode), With<ThisPlayer>>,
+) {
+    if !input.just_pressed(Action::PickBlock) {
+        return;
+    }
+    let (trans, mut inventory, game_mode) = player.single_mut();
+    if !game_mode.can_interact() {
+        return;
+    }
+    let Some((_, id)) = universe.raycast_block(trans, PLAYER_REACH) else {
+        return;
+    };
+    // outside of creative you can only pick what you already have
+    if !inventory.pick(id) && game_mode.unlimited_blocks() {
+        inventory.set_selected(ItemStack::full(id));
+    }
+}
+
+fn toggle_inventory(input: ActionInput, mut ui_state: ResMut<InventoryUiState>) {
+    if input.just_pressed(Action::Inventory) {
+        ui_state.show_inventory = !ui_state.show_inventory;
+    }
+}
+
+fn stack_label(universe: &Universe, stack: &ItemStack) -> String {
+    if stack.is_empty() {
+        String::from(" ")
+    } else {
+        format!("{}\n{}", universe.get_block_data_id(stack.block).name, stack.count)
+    }
+}
+
+const SLOT_SIZE: [f32; 2] = [56.0, 40.0];
+
+fn draw_hotbar(
+    mut egui: EguiContexts,
+    universe: Res<Universe>,
+    player: Query<(&Inventory, &GameMode), With<ThisPlayer>>,
+) {
+    let (inventory, game_mode) = player.single();
+    if !game_mode.can_interact() {
+        return;
+    }
+    egui::Area::new(egui::Id::new("hotbar"))
+        .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -12.0])
+        .show(egui.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for (i, stack) in inventory.hotbar.iter().enumerate() {
+                    let selected = i == inventory.selected as usize;
+                    let button = egui::Button::new(stack_label(&universe, stack))
+                        .min_size(SLOT_SIZE.into())
+                        .selected(selected);
+                    ui.add(button);
+                }
+            });
+        });
+}
+
+fn inventory_window(
+    mut egui: EguiContexts,
+    mut ui_state: ResMut<InventoryUiState>,
+    universe: Res<Universe>,
+    mut player: Query<(&mut Inventory, &GameMode), With<ThisPlayer>>,
+) {
+    let (mut inventory, game_mode) = player.single_mut();
+    if !game_mode.can_interact() {
+        return;
+    }
+    let mut open = ui_state.show_inventory;
+
+    egui::Window::new("Inventory").open(&mut open).show(egui.ctx_mut(), |ui| {
+        // the palette is the creative way of getting blocks
+        if game_mode.unlimited_blocks() {
+            ui.heading("Blocks");
+            ui.label("Click to put a stack in the selected hotbar slot");
+            ui.horizontal_wrapped(|ui| {
+                // air is always id 0, and you can't hold air, or the stand in for blocks that are gone
+                for (id, data) in universe.registered_blocks().into_iter().skip(1).filter(|(_, d)| d.name != UNKNOWN_BLOCK) {
+                    if ui.button(&data.name).clicked() {
+                        inventory.set_selected(ItemStack::full(id));
+                    }
+                }
+            });
+
+            ui.separator();
+        }
+        ui.heading("Storage");
+        ui.label("Click to swap with the selected hotbar slot");
+        egui::Grid::new("storage").show(ui, |ui| {
+            for slot in 0..STORAGE_SLOTS {
+                let label = stack_label(&universe, &inventory.storage[slot]);
+                if ui.add(egui::Button::new(label).min_size(SLOT_SIZE.into())).clicked() {
+                    inventory.swap_with_selected(slot);
+                }
+                if slot % HOTBAR_SLOTS == HOTBAR_SLOTS - 1 {
+                    ui.end_row();
+                }
+            }
+        });
+    });
+
+    ui_state.show_inventory = open;
+}
+
+pub struct InventoryPlugin;
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InventoryUiState>()
+            .add_systems(Update, (hotbar_selection, pick_block).run_if(not_paused))
+            .add_systems(Update, (toggle_inventory, draw_hotbar))
+            .add_systems(Update, inventory_window.run_if(|s: Res<InventoryUiState>| {s.show_inventory}));
+    }
+}
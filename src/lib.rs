@@ -0,0 +1,29 @@
+// the game itself lives here, main.rs just builds the app out of it
+// this way benches (and anything else) can link against the game's code
+
+// world data, generation and position math, these work without a window
+pub mod physics;
+pub mod position;
+pub mod world;
+pub mod chunk;
+pub mod terrain;
+pub mod schematic;
+pub mod cli;
+pub mod net;
+pub mod gamemode;
+
+// everything that needs rendering, input or egui
+#[cfg(feature = "client")]
+pub mod debug;
+#[cfg(feature = "client")]
+pub mod editing;
+#[cfg(feature = "client")]
+pub mod inventory;
+#[cfg(feature = "client")]
+pub mod player;
+#[cfg(feature = "client")]
+pub mod settings;
+#[cfg(feature = "client")]
+pub mod console;
+#[cfg(feature = "client")]
+pub mod scripting;
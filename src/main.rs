@@ -1,11 +1,3 @@
-mod debug;
-mod player;
-mod position;
-mod world;
-mod chunk;
-mod terrain;
-mod settings;
-
 use bevy::log::{Level, LogPlugin};
 use bevy::window::PrimaryWindow;
 use bevy::{
@@ -25,19 +17,57 @@ use bevy::{
     color::palettes::css::ALICE_BLUE
 };
 use bevy_egui::EguiPlugin;
-use world::universe::Universe;
-use world::block_materials::BlockMaterials;
-use world::block::*;
-use world::loading::ChunkEventsPlugin;
-use position::universe_transform::UniverseTransform;
+use dirlaku::cli;
+use dirlaku::net::DEFAULT_ADDRESS;
+use dirlaku::net::client::{ClientNetPlugin, ServerConnection};
+use dirlaku::world::universe::Universe;
+use dirlaku::world::block_materials::BlockMaterials;
+use dirlaku::world::block::*;
+use dirlaku::world::block_entity::BlockEntityPlugin;
+use dirlaku::world::edits::BlockEventsPlugin;
+use dirlaku::world::fluid::FluidPlugin;
+use dirlaku::world::loading::ChunkEventsPlugin;
+use dirlaku::position::universe_transform::UniverseTransform;
+
+use dirlaku::console::ConsolePlugin;
+use dirlaku::scripting::{load_mods, ScriptingPlugin};
+use dirlaku::debug::DebugTextPlugin;
+use dirlaku::editing::WorldEditPlugin;
+use dirlaku::inventory::InventoryPlugin;
+use dirlaku::physics::PhysicsPlugin;
+use dirlaku::player::PlayerPlugin;
+use dirlaku::player::gamemode::GameModePlugin;
+use dirlaku::settings::SettingsPlugin;
+use dirlaku::position::interpolation::InterpolationPlugin;
 
-use crate::debug::DebugTextPlugin;
-use crate::player::PlayerPlugin;
-use crate::settings::DEFAULT_SETTINGS;
+fn main() {
+    // `connect [address] [name]` plays on a server, any other arguments mean we're running a tool instead of the game
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let server = match args.first().map(|a| a.as_str()) {
+        None => None,
+        Some("connect") => {
+            let address = args.get(1).map(|a| a.as_str()).unwrap_or(DEFAULT_ADDRESS);
+            let name = args.get(2).map(|a| a.as_str()).unwrap_or("player");
+            match ServerConnection::connect(address, name) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    eprintln!("Couldn't connect to {}: {}", address, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(_) => std::process::exit(cli::run(&args)),
+    };
 
-use position::*;
+    let universe = if server.is_some() {
+        // chunks from the server go in a world of their own, emptied every time so nothing stale shows up
+        let path = std::env::temp_dir().join("chunkworld-remote");
+        let _ = std::fs::remove_dir_all(&path);
+        Universe::open(path)
+    } else {
+        Universe::new()
+    };
 
-fn main() {
     let image_plugin = ImagePlugin {
         default_sampler: ImageSamplerDescriptor {
             address_mode_u: Repeat,
@@ -47,18 +77,23 @@ fn main() {
         },
     };
 
-    App::new()
-        .add_plugins(DefaultPlugins.set(image_plugin).set(LogPlugin {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(image_plugin).set(LogPlugin {
             level: Level::INFO, ..default()
         }))
-        .add_plugins((PlayerPlugin, DebugTextPlugin, ChunkEventsPlugin))
+        .add_plugins((PlayerPlugin, PhysicsPlugin, InterpolationPlugin, DebugTextPlugin, ChunkEventsPlugin, BlockEventsPlugin, BlockEntityPlugin, WorldEditPlugin, InventoryPlugin, GameModePlugin, SettingsPlugin, ConsolePlugin, ScriptingPlugin))
         .add_plugins(EguiPlugin)
         .insert_resource(BlockMaterials::new())
-        .insert_resource(Universe::new())
-        .insert_resource(DEFAULT_SETTINGS)
+        .insert_resource(universe)
         .add_systems(Startup, set_window_title)
-        .add_systems(Startup, (build_block_registry, setup).chain())
-        .run();
+        .add_systems(Startup, (build_block_registry, load_mods, setup).chain());
+    if let Some(server) = server {
+        app.insert_resource(server).add_plugins(ClientNetPlugin);
+    } else {
+        // the server runs fluids when there is one
+        app.add_plugins(FluidPlugin);
+    }
+    app.run();
 }
 
 // Sets window title to proper name of game
@@ -71,21 +106,7 @@ fn set_window_title(mut window_query: Query<&mut Window, With<PrimaryWindow>>) {
 fn build_block_registry(
     universe: Res<Universe>,
 ) {
-    // do not register air here, the universe init handles that automatically to ensure air is always id 0
-    universe.register_block(
-        BlockData {
-            name: String::from("stone"),
-            block_type: BlockType::OpaqueSolid,
-            texture_file: String::from("textures/block/stone.png")
-        },
-    );
-    universe.register_block(
-        BlockData {
-            name: String::from("dirt"),
-            block_type: BlockType::OpaqueSolid,
-            texture_file: String::from("textures/block/dirt.png")
-        },
-    );
+    register_default_blocks(&universe);
 }
 
 // summons test shit
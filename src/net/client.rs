@@ -0,0 +1,226 @@
+// the game's side of a connection to a dedicated server
+// chunks from the server are written into a local Universe, which the usual chunk loading then meshes
+
+use std::collections::HashMap;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use super::protocol::{ClientMessage, PlayerPose, ServerMessage, PROTOCOL_VERSION};
+use super::Connection;
+use crate::chunk::chunk::{BlockId, CHUNK_SIZE, CHUNK_SIZE_I32};
+use crate::player::gamemode::GameMode;
+use crate::player::{Player, ThisPlayer};
+use crate::position::universe_location::UniverseLocation;
+use crate::position::universe_transform::UniverseTransform;
+use crate::settings::Settings;
+use crate::world::edits::{BlockChangedEvent, EditCause};
+use crate::world::loading::ChunkRemeshEvent;
+use crate::world::region::Region;
+use crate::world::universe::{Universe, UNKNOWN_BLOCK};
+
+#[derive(Resource)]
+pub struct ServerConnection {
+    conn: Connection,
+    // our player id, once the server has welcomed us
+    pub id: Option<u32>,
+    ids: BlockIdMap,
+}
+
+impl ServerConnection {
+    pub fn connect(address: &str, name: &str) -> std::io::Result<Self> {
+        let mut conn = Connection::connect(address)?;
+        conn.send(&ClientMessage::Handshake { version: PROTOCOL_VERSION });
+        conn.send(&ClientMessage::Login { name: String::from(name) });
+        Ok(Self { conn, id: None, ids: BlockIdMap::default() })
+    }
+
+    // the edit has already been made locally, the server answers with what really happened
+    fn request_block(&mut self, pos: IVec3, block: BlockId) {
+        let Some(block) = self.ids.to_server.get(&block).copied() else {
+            warn!("Not sending the edit at {} {} {}, the server doesn't have that block", pos.x, pos.y, pos.z);
+            return;
+        };
+        self.conn.send(&ClientMessage::BlockChange { pos, block });
+    }
+}
+
+// the server's block ids and ours, which only match if both sides registered the same blocks the same way
+#[derive(Default)]
+struct BlockIdMap {
+    to_local: HashMap<BlockId, BlockId>,
+    to_server: HashMap<BlockId, BlockId>,
+}
+
+impl BlockIdMap {
+    fn new(universe: &Universe, blocks: &[(String, BlockId, u32)]) -> Self {
+        let mut map = Self::default();
+        for (name, server_base, states) in blocks {
+            // a block with different states can't be lined up, its ids come out as unknown
+            let local_base = universe.try_block_id_from_name(name)
+                .filter(|base| universe.get_block_data_id(*base).state_count() == *states)
+                .filter(|_| server_base.0.checked_add(*states).is_some());
+            let Some(local_base) = local_base else {
+                warn!("The server's \"{}\" block doesn't match any of ours", name);
+                continue;
+            };
+            for state in 0..*states {
+                map.to_local.insert(BlockId(server_base.0 + state), BlockId(local_base.0 + state));
+                map.to_server.insert(BlockId(local_base.0 + state), BlockId(server_base.0 + state));
+            }
+        }
+        map
+    }
+
+    // anything we can't make sense of is the unknown block, rather than an id that'd mean something else here
+    fn to_local(&self, universe: &Universe, id: BlockId) -> BlockId {
+        self.to_local.get(&id)
+            .copied()
+            .filter(|local| universe.is_known_block(*local))
+            .unwrap_or_else(|| universe.block_id_from_name(String::from(UNKNOWN_BLOCK)))
+    }
+}
+
+// somebody else on the server, drawn like any other Player
+#[derive(Component)]
+pub struct RemotePlayer(pub u32);
+
+#[derive(Resource, Default)]
+struct RemotePlayers(HashMap<u32, Entity>);
+
+fn pose_transform(pose: &PlayerPose) -> UniverseTransform {
+    UniverseTransform {
+        loc: UniverseLocation::from_dim_xyz(pose.dimension, pose.position),
+        pitch: pose.pitch,
+        yaw: pose.yaw,
+    }
+}
+
+fn lost_connection(ev_exit: &mut EventWriter<AppExit>, reason: impl std::fmt::Display) {
+    error!("Lost connection to the server: {}", reason);
+    ev_exit.send(AppExit::error());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn receive_from_server(
+    mut server: ResMut<ServerConnection>,
+    universe: Res<Universe>,
+    mut remote: ResMut<RemotePlayers>,
+    mut players: Query<&mut UniverseTransform, With<RemotePlayer>>,
+    mut our_mode: Query<&mut GameMode, With<ThisPlayer>>,
+    mut commands: Commands,
+    mut ev_remesh: EventWriter<ChunkRemeshEvent>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    let messages = match server.conn.receive::<ServerMessage>() {
+        Ok(messages) => messages,
+        Err(e) => return lost_connection(&mut ev_exit, e),
+    };
+
+    for message in messages {
+        match message {
+            ServerMessage::LoginSuccess { id, seed, game_mode, blocks } => {
+                info!("Joined as player {} (seed {}) in {}", id, seed, game_mode.name());
+                server.id = Some(id);
+                server.ids = BlockIdMap::new(&universe, &blocks);
+                // the server checks edits against its own idea of our mode, so go along with it
+                for mut mode in our_mode.iter_mut() {
+                    *mode = game_mode;
+                }
+            }
+            ServerMessage::ChunkData { coords, mut chunk } => {
+                // chunks only have a handful of different blocks, no need to look each one up again
+                let mut mapped: HashMap<BlockId, BlockId> = HashMap::new();
+                let size = CHUNK_SIZE as u32;
+                for x in 0..size {
+                    for y in 0..size {
+                        for z in 0..size {
+                            let id = chunk.get(x, y, z);
+                            let local = *mapped.entry(id).or_insert_with(|| server.ids.to_local(&universe, id));
+                            chunk.place(local, (x, y, z));
+                        }
+                    }
+                }
+                // a whole new chunk isn't an edit, so nothing else would remesh it
+                universe.flush_chunk(&coords, &chunk);
+                ev_remesh.send(ChunkRemeshEvent(coords));
+            }
+            ServerMessage::BlockChange { pos, block } => {
+                universe.set_block(pos, server.ids.to_local(&universe, block), EditCause::Server);
+            }
+            ServerMessage::MultiBlockChange { chunk, changes } => {
+                let origin = chunk * CHUNK_SIZE_I32;
+                let changes: HashMap<IVec3, BlockId> = changes.into_iter()
+                    .map(|(local, block)| (origin + local.as_ivec3(), server.ids.to_local(&universe, block)))
+                    .collect();
+                let whole = Region::from_corners(origin, origin + IVec3::splat(CHUNK_SIZE_I32 - 1));
+                // one pass over the chunk instead of reading and writing it back for every change
+                universe.edit_region(&whole, EditCause::Server, |pos, _| changes.get(&pos).copied());
+            }
+            ServerMessage::Disconnect { reason } => return lost_connection(&mut ev_exit, reason),
+            ServerMessage::PlayerMove { id, pose } => {
+                let trans = pose_transform(&pose);
+                if let Some(mut current) = remote.0.get(&id).and_then(|e| players.get_mut(*e).ok()) {
+                    *current = trans;
+                    continue;
+                }
+                let e = commands.spawn((Player, RemotePlayer(id), trans)).id();
+                remote.0.insert(id, e);
+            }
+            ServerMessage::PlayerLeft { id } => {
+                if let Some(e) = remote.0.remove(&id) {
+                    commands.entity(e).despawn();
+                }
+            }
+        }
+    }
+}
+
+fn send_to_server(
+    mut server: ResMut<ServerConnection>,
+    settings: Res<Settings>,
+    player: Query<&UniverseTransform, With<ThisPlayer>>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    let trans = player.single();
+    server.conn.send(&ClientMessage::PlayerMove {
+        pose: PlayerPose {
+            dimension: trans.loc.dimension,
+            position: trans.loc.position,
+            pitch: trans.pitch,
+            yaw: trans.yaw,
+        },
+        horizontal_view: settings.horizontal_render_distance,
+        vertical_view: settings.vertical_render_distance,
+    });
+    if let Err(e) = server.conn.flush() {
+        lost_connection(&mut ev_exit, e);
+    }
+}
+
+// anything changed on this side gets passed on, whatever the server sent us is already there
+fn forward_edits(mut server: ResMut<ServerConnection>, mut ev_changed: EventReader<BlockChangedEvent>) {
+    for BlockChangedEvent(edit) in ev_changed.read() {
+        if edit.cause != EditCause::Server {
+            server.request_block(edit.pos, edit.new);
+        }
+    }
+}
+
+// lets the server know we're going instead of leaving it to notice the connection closing
+fn disconnect_on_exit(mut server: ResMut<ServerConnection>) {
+    server.conn.send(&ClientMessage::Disconnect { reason: String::from("Quit") });
+    let _ = server.conn.flush();
+}
+
+// expects a ServerConnection to be inserted by whoever adds this
+pub struct ClientNetPlugin;
+impl Plugin for ClientNetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RemotePlayers>()
+            .add_systems(PreUpdate, receive_from_server)
+            .add_systems(Update, forward_edits)
+            .add_systems(FixedPostUpdate, send_to_server)
+            .add_systems(Last, disconnect_on_exit.run_if(on_event::<AppExit>()));
+    }
+}
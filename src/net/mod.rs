@@ -0,0 +1,97 @@
+// multiplayer, a dedicated server owns the Universe and game clients connect to it over tcp
+// each message is framed as a little endian u32 length followed by the message itself, see protocol.rs
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+pub mod protocol;
+pub mod server;
+#[cfg(feature = "client")]
+pub mod client;
+
+use protocol::Message;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:25570";
+
+// a chunk compresses to a lot less than this, anything bigger is garbage
+const MAX_FRAME: usize = 1 << 20;
+
+// a nonblocking tcp stream with buffers on both ends, so neither side ever waits on the other
+pub struct Connection {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self { stream, incoming: Vec::new(), outgoing: Vec::new() })
+    }
+
+    pub fn connect(address: &str) -> io::Result<Self> {
+        Self::new(TcpStream::connect(address)?)
+    }
+
+    pub fn send<M: Message>(&mut self, message: &M) {
+        let start = self.outgoing.len();
+        self.outgoing.extend_from_slice(&[0; 4]);
+        message.encode(&mut self.outgoing);
+        let len = (self.outgoing.len() - start - 4) as u32;
+        LittleEndian::write_u32(&mut self.outgoing[start..start + 4], len);
+    }
+
+    // bytes queued up that the socket hasn't taken yet
+    pub fn backlog(&self) -> usize {
+        self.outgoing.len()
+    }
+
+    // writes as much as the socket will take right now
+    pub fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => {
+                    self.outgoing.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    // every whole message that has arrived so far, an error means the connection is done for
+    pub fn receive<M: Message>(&mut self) -> io::Result<Vec<M>> {
+        let mut buf = [0; 16 * 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.incoming.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut messages = Vec::new();
+        let mut read = 0;
+        while self.incoming.len() - read >= 4 {
+            let len = LittleEndian::read_u32(&self.incoming[read..]) as usize;
+            if len > MAX_FRAME {
+                return Err(io::Error::new(ErrorKind::InvalidData, format!("message of {} bytes is too big", len)));
+            }
+            if self.incoming.len() - read - 4 < len {
+                break; // the rest hasn't arrived yet
+            }
+            messages.push(M::decode(&self.incoming[read + 4..read + 4 + len])?);
+            read += 4 + len;
+        }
+        self.incoming.drain(..read);
+        Ok(messages)
+    }
+}
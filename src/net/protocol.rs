@@ -0,0 +1,625 @@
+// the messages clients and the server send each other, and how they're laid out on the wire
+//
+// every message is framed by Connection as a little endian u32 length, then the message itself,
+// which starts with a one byte type and is followed by that type's fields, also little endian:
+//   ivec3    3 x i32
+//   pose     u32 dimension, 3 x f64 position, f64 pitch, f64 yaw
+//   string   u16 byte length, then that much utf-8
+//   block    u32 block id
+//   mode     u8 game mode
+//
+// a connection starts with the client sending Handshake, then Login
+// the server answers with LoginSuccess, or Disconnect if the versions don't match
+// LoginSuccess has the server's block table, block ids in every message after it are the server's
+// either side can send Disconnect at any point, with a reason for the other side to show
+//
+// chunk data is palette compressed: a u16 palette length, that many block ids, then a u8 bits per block,
+// then the blocks as indices into the palette, packed into u64s with as many whole indices per u64 as fit,
+// lowest bits first, in x, z, y order (the same order Chunk stores them in)
+// a chunk that's all one block has 0 bits per block and no u64s at all
+//
+// decoding never panics, anything malformed (truncated, trailing bytes, out of range) comes back as InvalidData
+
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read};
+
+use bevy::math::f64::DVec3;
+use bevy::prelude::{IVec3, UVec3};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::chunk::chunk::{BlockId, Chunk, CHUNK_SIZE};
+use crate::gamemode::GameMode;
+
+// bumped whenever a message changes, a client and server only talk if theirs match
+pub const PROTOCOL_VERSION: u32 = 3;
+
+const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+const MAX_STRING: usize = 256;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+pub trait Message: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    fn decode(bytes: &[u8]) -> io::Result<Self>;
+}
+
+fn write_ivec3(out: &mut Vec<u8>, v: IVec3) {
+    for c in v.to_array() {
+        out.write_i32::<LittleEndian>(c).unwrap();
+    }
+}
+
+fn read_ivec3(r: &mut Cursor<&[u8]>) -> io::Result<IVec3> {
+    Ok(IVec3::new(r.read_i32::<LittleEndian>()?, r.read_i32::<LittleEndian>()?, r.read_i32::<LittleEndian>()?))
+}
+
+fn write_dvec3(out: &mut Vec<u8>, v: DVec3) {
+    for c in v.to_array() {
+        out.write_f64::<LittleEndian>(c).unwrap();
+    }
+}
+
+fn read_dvec3(r: &mut Cursor<&[u8]>) -> io::Result<DVec3> {
+    Ok(DVec3::new(r.read_f64::<LittleEndian>()?, r.read_f64::<LittleEndian>()?, r.read_f64::<LittleEndian>()?))
+}
+
+fn write_block(out: &mut Vec<u8>, block: BlockId) {
+    out.write_u32::<LittleEndian>(block.0).unwrap();
+}
+
+fn read_block(r: &mut Cursor<&[u8]>) -> io::Result<BlockId> {
+    Ok(BlockId(r.read_u32::<LittleEndian>()?))
+}
+
+fn read_game_mode(r: &mut Cursor<&[u8]>) -> io::Result<GameMode> {
+    let n = r.read_u8()?;
+    GameMode::from_u32(n as u32).ok_or_else(|| invalid(format!("unknown game mode {}", n)))
+}
+
+// longer strings are cut off (on a char boundary) rather than refused
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    let mut end = s.len().min(MAX_STRING);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    out.write_u16::<LittleEndian>(end as u16).unwrap();
+    out.extend_from_slice(&s.as_bytes()[..end]);
+}
+
+fn read_string(r: &mut Cursor<&[u8]>) -> io::Result<String> {
+    let len = r.read_u16::<LittleEndian>()? as usize;
+    if len > MAX_STRING {
+        return Err(invalid(format!("string of {} bytes is too long", len)));
+    }
+    let mut bytes = vec![0; len];
+    r.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid("string isn't utf-8"))
+}
+
+// so a message with junk on the end is noticed instead of half understood
+fn finish<T>(r: &Cursor<&[u8]>, message: T) -> io::Result<T> {
+    let extra = r.get_ref().len() as u64 - r.position();
+    if extra > 0 {
+        return Err(invalid(format!("{} bytes left over after the message", extra)));
+    }
+    Ok(message)
+}
+
+// where a player is and which way they're looking
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerPose {
+    pub dimension: u32,
+    pub position: DVec3,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+impl PlayerPose {
+    fn write(&self, out: &mut Vec<u8>) {
+        out.write_u32::<LittleEndian>(self.dimension).unwrap();
+        write_dvec3(out, self.position);
+        out.write_f64::<LittleEndian>(self.pitch).unwrap();
+        out.write_f64::<LittleEndian>(self.yaw).unwrap();
+    }
+
+    fn read(r: &mut Cursor<&[u8]>) -> io::Result<Self> {
+        Ok(Self {
+            dimension: r.read_u32::<LittleEndian>()?,
+            position: read_dvec3(r)?,
+            pitch: r.read_f64::<LittleEndian>()?,
+            yaw: r.read_f64::<LittleEndian>()?,
+        })
+    }
+}
+
+// how many bits it takes to tell apart this many palette entries
+fn bits_for(palette_len: usize) -> u8 {
+    if palette_len <= 1 {
+        0
+    } else {
+        (usize::BITS - (palette_len - 1).leading_zeros()) as u8
+    }
+}
+
+fn packed_words(bits: u8) -> usize {
+    if bits == 0 {
+        return 0;
+    }
+    let per_word = 64 / bits as usize;
+    CHUNK_VOLUME.div_ceil(per_word)
+}
+
+// calls f with every block in x, z, y order
+fn for_each_block(mut f: impl FnMut(usize, (u32, u32, u32))) {
+    let size = CHUNK_SIZE as u32;
+    let mut i = 0;
+    for x in 0..size {
+        for z in 0..size {
+            for y in 0..size {
+                f(i, (x, y, z));
+                i += 1;
+            }
+        }
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, chunk: &Chunk) {
+    let mut palette: Vec<BlockId> = Vec::new();
+    let mut palette_index: HashMap<BlockId, u16> = HashMap::new();
+    let mut indices = vec![0u16; CHUNK_VOLUME];
+    for_each_block(|i, (x, y, z)| {
+        let block = chunk.get(x, y, z);
+        indices[i] = *palette_index.entry(block).or_insert_with(|| {
+            palette.push(block);
+            (palette.len() - 1) as u16
+        });
+    });
+
+    out.write_u16::<LittleEndian>(palette.len() as u16).unwrap();
+    for block in &palette {
+        write_block(out, *block);
+    }
+    let bits = bits_for(palette.len());
+    out.push(bits);
+    if bits == 0 {
+        return;
+    }
+
+    let per_word = 64 / bits as usize;
+    for word_indices in indices.chunks(per_word) {
+        let mut word = 0u64;
+        for (slot, index) in word_indices.iter().enumerate() {
+            word |= (*index as u64) << (slot * bits as usize);
+        }
+        out.write_u64::<LittleEndian>(word).unwrap();
+    }
+}
+
+fn read_chunk(r: &mut Cursor<&[u8]>) -> io::Result<Box<Chunk>> {
+    let palette_len = r.read_u16::<LittleEndian>()? as usize;
+    if palette_len == 0 || palette_len > CHUNK_VOLUME {
+        return Err(invalid(format!("chunk palette can't have {} entries", palette_len)));
+    }
+    let mut palette = Vec::with_capacity(palette_len);
+    for _ in 0..palette_len {
+        palette.push(read_block(r)?);
+    }
+    let bits = r.read_u8()?;
+    if bits != bits_for(palette_len) {
+        return Err(invalid(format!("{} bits per block doesn't match a palette of {}", bits, palette_len)));
+    }
+
+    let mut chunk = Box::new(Chunk::new());
+    if bits == 0 {
+        for_each_block(|_, pos| chunk.place(palette[0], pos));
+        return Ok(chunk);
+    }
+
+    let mut words = Vec::with_capacity(packed_words(bits));
+    for _ in 0..packed_words(bits) {
+        words.push(r.read_u64::<LittleEndian>()?);
+    }
+    let per_word = 64 / bits as usize;
+    let mask = (1u64 << bits) - 1;
+    let mut bad_index = None;
+    for_each_block(|i, pos| {
+        let index = ((words[i / per_word] >> ((i % per_word) * bits as usize)) & mask) as usize;
+        match palette.get(index) {
+            Some(block) => chunk.place(*block, pos),
+            None => bad_index = Some(index),
+        }
+    });
+    match bad_index {
+        Some(index) => Err(invalid(format!("palette index {} is past the end of a palette of {}", index, palette_len))),
+        None => Ok(chunk),
+    }
+}
+
+// a block's position within its chunk, in the 15 bits a multi block change uses
+fn pack_local(local: UVec3) -> u16 {
+    ((local.x << 10) | (local.y << 5) | local.z) as u16
+}
+
+fn unpack_local(packed: u16) -> io::Result<UVec3> {
+    if packed >> 15 != 0 {
+        return Err(invalid(format!("{:#x} isn't a position in a chunk", packed)));
+    }
+    let packed = packed as u32;
+    Ok(UVec3::new(packed >> 10, (packed >> 5) & 31, packed & 31))
+}
+
+#[derive(Debug)]
+pub enum ClientMessage {
+    Handshake { version: u32 },
+    Login { name: String },
+    // sent every tick, the server streams chunks around this
+    PlayerMove { pose: PlayerPose, horizontal_view: u8, vertical_view: u8 },
+    // a request, the server decides whether it actually happens
+    BlockChange { pos: IVec3, block: BlockId },
+    Disconnect { reason: String },
+}
+
+impl Message for ClientMessage {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ClientMessage::Handshake { version } => {
+                out.push(0);
+                out.write_u32::<LittleEndian>(*version).unwrap();
+            }
+            ClientMessage::Login { name } => {
+                out.push(1);
+                write_string(out, name);
+            }
+            ClientMessage::PlayerMove { pose, horizontal_view, vertical_view } => {
+                out.push(2);
+                pose.write(out);
+                out.push(*horizontal_view);
+                out.push(*vertical_view);
+            }
+            ClientMessage::BlockChange { pos, block } => {
+                out.push(3);
+                write_ivec3(out, *pos);
+                write_block(out, *block);
+            }
+            ClientMessage::Disconnect { reason } => {
+                out.push(4);
+                write_string(out, reason);
+            }
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut r = Cursor::new(bytes);
+        let message = match r.read_u8()? {
+            0 => ClientMessage::Handshake { version: r.read_u32::<LittleEndian>()? },
+            1 => ClientMessage::Login { name: read_string(&mut r)? },
+            2 => ClientMessage::PlayerMove {
+                pose: PlayerPose::read(&mut r)?,
+                horizontal_view: r.read_u8()?,
+                vertical_view: r.read_u8()?,
+            },
+            3 => ClientMessage::BlockChange { pos: read_ivec3(&mut r)?, block: read_block(&mut r)? },
+            4 => ClientMessage::Disconnect { reason: read_string(&mut r)? },
+            t => return Err(invalid(format!("unknown client message type {}", t))),
+        };
+        finish(&r, message)
+    }
+}
+
+pub enum ServerMessage {
+    // the game mode decides what edits the server lets through
+    // blocks are each block's name, base id and number of states, so the client can line its own ids up with them
+    LoginSuccess { id: u32, seed: u64, game_mode: GameMode, blocks: Vec<(String, BlockId, u32)> },
+    ChunkData { coords: IVec3, chunk: Box<Chunk> },
+    // authoritative edits, only for chunks the client has been sent
+    BlockChange { pos: IVec3, block: BlockId },
+    // several edits in the same chunk, positions are within the chunk
+    MultiBlockChange { chunk: IVec3, changes: Vec<(UVec3, BlockId)> },
+    PlayerMove { id: u32, pose: PlayerPose },
+    PlayerLeft { id: u32 },
+    Disconnect { reason: String },
+}
+
+impl Message for ServerMessage {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            ServerMessage::LoginSuccess { id, seed, game_mode, blocks } => {
+                out.push(0);
+                out.write_u32::<LittleEndian>(*id).unwrap();
+                out.write_u64::<LittleEndian>(*seed).unwrap();
+                out.push(*game_mode as u8);
+                out.write_u16::<LittleEndian>(blocks.len() as u16).unwrap();
+                for (name, base, states) in blocks {
+                    write_string(out, name);
+                    write_block(out, *base);
+                    out.write_u32::<LittleEndian>(*states).unwrap();
+                }
+            }
+            ServerMessage::ChunkData { coords, chunk } => {
+                out.push(1);
+                write_ivec3(out, *coords);
+                write_chunk(out, chunk);
+            }
+            ServerMessage::BlockChange { pos, block } => {
+                out.push(2);
+                write_ivec3(out, *pos);
+                write_block(out, *block);
+            }
+            ServerMessage::MultiBlockChange { chunk, changes } => {
+                out.push(3);
+                write_ivec3(out, *chunk);
+                out.write_u16::<LittleEndian>(changes.len() as u16).unwrap();
+                for (local, block) in changes {
+                    out.write_u16::<LittleEndian>(pack_local(*local)).unwrap();
+                    write_block(out, *block);
+                }
+            }
+            ServerMessage::PlayerMove { id, pose } => {
+                out.push(4);
+                out.write_u32::<LittleEndian>(*id).unwrap();
+                pose.write(out);
+            }
+            ServerMessage::PlayerLeft { id } => {
+                out.push(5);
+                out.write_u32::<LittleEndian>(*id).unwrap();
+            }
+            ServerMessage::Disconnect { reason } => {
+                out.push(6);
+                write_string(out, reason);
+            }
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let mut r = Cursor::new(bytes);
+        let message = match r.read_u8()? {
+            0 => {
+                let id = r.read_u32::<LittleEndian>()?;
+                let seed = r.read_u64::<LittleEndian>()?;
+                let game_mode = read_game_mode(&mut r)?;
+                let count = r.read_u16::<LittleEndian>()? as usize;
+                // an empty name, a base and a state count is 10 bytes
+                if count > bytes.len() / 10 {
+                    return Err(invalid(format!("{} blocks can't fit in {} bytes", count, bytes.len())));
+                }
+                let mut blocks = Vec::with_capacity(count);
+                for _ in 0..count {
+                    blocks.push((read_string(&mut r)?, read_block(&mut r)?, r.read_u32::<LittleEndian>()?));
+                }
+                ServerMessage::LoginSuccess { id, seed, game_mode, blocks }
+            }
+            1 => ServerMessage::ChunkData { coords: read_ivec3(&mut r)?, chunk: read_chunk(&mut r)? },
+            2 => ServerMessage::BlockChange { pos: read_ivec3(&mut r)?, block: read_block(&mut r)? },
+            3 => {
+                let chunk = read_ivec3(&mut r)?;
+                let count = r.read_u16::<LittleEndian>()? as usize;
+                // each change is 6 bytes, don't trust a count the message can't hold
+                if count > bytes.len() / 6 {
+                    return Err(invalid(format!("{} block changes can't fit in {} bytes", count, bytes.len())));
+                }
+                let mut changes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    changes.push((unpack_local(r.read_u16::<LittleEndian>()?)?, read_block(&mut r)?));
+                }
+                ServerMessage::MultiBlockChange { chunk, changes }
+            }
+            4 => ServerMessage::PlayerMove { id: r.read_u32::<LittleEndian>()?, pose: PlayerPose::read(&mut r)? },
+            5 => ServerMessage::PlayerLeft { id: r.read_u32::<LittleEndian>()? },
+            6 => ServerMessage::Disconnect { reason: read_string(&mut r)? },
+            t => return Err(invalid(format!("unknown server message type {}", t))),
+        };
+        finish(&r, message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use zerocopy::AsBytes;
+
+    fn encoded<M: Message>(message: &M) -> Vec<u8> {
+        let mut out = Vec::new();
+        message.encode(&mut out);
+        out
+    }
+
+    // neither message type can be compared directly, so a round trip has to come back out as the same bytes
+    fn round_trip<M: Message>(message: &M) -> M {
+        let bytes = encoded(message);
+        let decoded = M::decode(&bytes).unwrap();
+        assert_eq!(encoded(&decoded), bytes);
+        decoded
+    }
+
+    fn pose() -> PlayerPose {
+        PlayerPose { dimension: 2, position: DVec3::new(-12.5, 70.25, 1e9), pitch: -0.75, yaw: 3.0 }
+    }
+
+    fn chunk_of(block: impl Fn(u32, u32, u32) -> BlockId) -> Box<Chunk> {
+        let mut chunk = Box::new(Chunk::new());
+        for_each_block(|_, (x, y, z)| chunk.place(block(x, y, z), (x, y, z)));
+        chunk
+    }
+
+    fn client_messages() -> Vec<ClientMessage> {
+        vec![
+            ClientMessage::Handshake { version: PROTOCOL_VERSION },
+            ClientMessage::Login { name: String::from("Ärger") },
+            ClientMessage::PlayerMove { pose: pose(), horizontal_view: 12, vertical_view: 255 },
+            ClientMessage::BlockChange { pos: IVec3::new(-1, 64, i32::MAX), block: BlockId(17) },
+            ClientMessage::Disconnect { reason: String::from("Quit") },
+        ]
+    }
+
+    fn server_messages() -> Vec<ServerMessage> {
+        vec![
+            ServerMessage::LoginSuccess {
+                id: 3,
+                seed: u64::MAX - 1,
+                game_mode: GameMode::Adventure,
+                blocks: vec![(String::from("air"), BlockId(0), 1), (String::from("log"), BlockId(7), 3)],
+            },
+            ServerMessage::ChunkData { coords: IVec3::new(-4, 0, 9), chunk: chunk_of(|_, y, _| BlockId(y % 3)) },
+            ServerMessage::BlockChange { pos: IVec3::new(5, -6, 7), block: BlockId(2) },
+            ServerMessage::MultiBlockChange {
+                chunk: IVec3::new(1, 2, 3),
+                changes: vec![(UVec3::ZERO, BlockId(1)), (UVec3::splat(31), BlockId(0)), (UVec3::new(31, 0, 7), BlockId(9))],
+            },
+            ServerMessage::PlayerMove { id: 1, pose: pose() },
+            ServerMessage::PlayerLeft { id: 8 },
+            ServerMessage::Disconnect { reason: String::from("Server closed") },
+        ]
+    }
+
+    #[test]
+    fn client_messages_round_trip() {
+        for message in client_messages() {
+            let decoded = round_trip(&message);
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", message));
+        }
+    }
+
+    #[test]
+    fn server_messages_round_trip() {
+        for message in server_messages() {
+            round_trip(&message);
+        }
+    }
+
+    fn chunk_round_trip(chunk: Box<Chunk>, bits: u8) {
+        let message = ServerMessage::ChunkData { coords: IVec3::ZERO, chunk };
+        let bytes = encoded(&message);
+        let ServerMessage::ChunkData { chunk: original, .. } = &message else { unreachable!() };
+        let ServerMessage::ChunkData { chunk: decoded, .. } = round_trip(&message) else {
+            panic!("came back as a different message");
+        };
+        assert_eq!(decoded.as_bytes(), original.as_bytes());
+
+        // type, coords, palette length, then the palette, and the bits per block comes right after it
+        let palette_len = u16::from_le_bytes([bytes[13], bytes[14]]) as usize;
+        assert_eq!(bytes[15 + palette_len * 4], bits);
+        assert_eq!(bytes.len(), 16 + palette_len * 4 + packed_words(bits) * 8);
+    }
+
+    #[test]
+    fn uniform_chunk_has_no_block_data() {
+        chunk_round_trip(chunk_of(|_, _, _| BlockId(5)), 0);
+    }
+
+    #[test]
+    fn two_block_chunk_uses_one_bit() {
+        chunk_round_trip(chunk_of(|x, y, z| BlockId((x + y + z) % 2)), 1);
+    }
+
+    #[test]
+    fn many_block_chunk() {
+        // 300 different blocks needs 9 bits, which doesn't divide 64 evenly
+        chunk_round_trip(chunk_of(|x, y, z| BlockId((x * 1024 + z * 32 + y) % 300 + 1000)), 9);
+    }
+
+    #[test]
+    fn every_truncation_fails() {
+        for bytes in client_messages().iter().map(encoded) {
+            for len in 0..bytes.len() {
+                assert!(ClientMessage::decode(&bytes[..len]).is_err());
+            }
+        }
+        for bytes in server_messages().iter().map(encoded) {
+            for len in 0..bytes.len() {
+                assert!(ServerMessage::decode(&bytes[..len]).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_bytes_fail() {
+        for mut bytes in client_messages().iter().map(encoded) {
+            bytes.push(0);
+            assert!(ClientMessage::decode(&bytes).is_err());
+        }
+        for mut bytes in server_messages().iter().map(encoded) {
+            bytes.push(0);
+            assert!(ServerMessage::decode(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn oversized_counts_fail() {
+        // a multi block change claiming more changes than it holds
+        let mut bytes = vec![3];
+        write_ivec3(&mut bytes, IVec3::ZERO);
+        bytes.extend_from_slice(&u16::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0; 12]);
+        assert!(ServerMessage::decode(&bytes).is_err());
+
+        // a block table claiming more blocks than it holds
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&[0; 12]);
+        bytes.push(GameMode::Creative as u8);
+        bytes.extend_from_slice(&u16::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0; 20]);
+        assert!(ServerMessage::decode(&bytes).is_err());
+
+        // a string longer than strings are allowed to be
+        let mut bytes = vec![1];
+        bytes.extend_from_slice(&(MAX_STRING as u16 + 1).to_le_bytes());
+        bytes.extend(std::iter::repeat_n(b'a', MAX_STRING + 1));
+        assert!(ClientMessage::decode(&bytes).is_err());
+
+        // chunk palettes that are empty, or bigger than a chunk
+        for palette_len in [0, u16::MAX] {
+            let mut bytes = vec![1];
+            write_ivec3(&mut bytes, IVec3::ZERO);
+            bytes.extend_from_slice(&palette_len.to_le_bytes());
+            bytes.extend_from_slice(&[0; 64]);
+            assert!(ServerMessage::decode(&bytes).is_err());
+        }
+
+        // bits per block that don't match the palette
+        let mut bytes = encoded(&ServerMessage::ChunkData { coords: IVec3::ZERO, chunk: chunk_of(|_, _, _| BlockId(0)) });
+        *bytes.last_mut().unwrap() = 3;
+        assert!(ServerMessage::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn bad_values_fail() {
+        // a palette index past the end of a three entry palette, which gets two bits
+        let mut bytes = encoded(&ServerMessage::ChunkData { coords: IVec3::ZERO, chunk: chunk_of(|x, _, _| BlockId(x % 3)) });
+        let len = bytes.len();
+        bytes[len - 1] = 0xff;
+        assert!(ServerMessage::decode(&bytes).is_err());
+
+        // the game mode comes after the type, id and seed
+        let mut bytes = encoded(&server_messages()[0]);
+        bytes[13] = 200;
+        assert!(ServerMessage::decode(&bytes).is_err());
+
+        assert!(ClientMessage::decode(&[99]).is_err());
+        assert!(ServerMessage::decode(&[99]).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn random_bytes_dont_panic(bytes in vec(any::<u8>(), 0..256)) {
+            let _ = ClientMessage::decode(&bytes);
+            let _ = ServerMessage::decode(&bytes);
+        }
+
+        #[test]
+        fn random_trailing_bytes_fail(which in 0..12usize, extra in vec(any::<u8>(), 1..64)) {
+            if which < 5 {
+                let mut bytes = encoded(&client_messages()[which]);
+                bytes.extend(extra);
+                prop_assert!(ClientMessage::decode(&bytes).is_err());
+            } else {
+                let mut bytes = encoded(&server_messages()[which - 5]);
+                bytes.extend(extra);
+                prop_assert!(ServerMessage::decode(&bytes).is_err());
+            }
+        }
+    }
+}
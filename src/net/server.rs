@@ -0,0 +1,355 @@
+// the dedicated server side, everything here runs without a window
+
+use std::collections::{HashMap, HashSet};
+use std::net::{SocketAddr, TcpListener};
+
+use bevy::prelude::*;
+use zerocopy::FromBytes;
+
+use super::protocol::{ClientMessage, PlayerPose, ServerMessage, PROTOCOL_VERSION};
+use super::Connection;
+use crate::chunk::chunk::{split_block_position, BlockId, Chunk, CHUNK_SIZE_I32};
+use crate::gamemode::GameMode;
+use crate::position::universe_transform::UniverseTransform;
+use crate::terrain::terraingen::generate_chunk;
+use crate::world::edits::{BlockChangedEvent, EditCause};
+use crate::world::region::Region;
+use crate::world::universe::Universe;
+
+pub const TICK_RATE: f64 = 30.0;
+
+// chunks sent to each client per tick, so one player flying around can't starve everyone else
+const CHUNKS_PER_TICK: usize = 4;
+// stop sending chunks to a client that isn't keeping up
+const MAX_BACKLOG: usize = 4 << 20;
+// whatever a client asks for, it doesn't get more than this
+const MAX_HORIZONTAL_VIEW: i32 = 16;
+const MAX_VERTICAL_VIEW: i32 = 8;
+// most changes in one multi block change message
+const MAX_CHANGES_PER_MESSAGE: usize = 4096;
+// a bit more than the player's reach, the pose we have is always a little behind
+const MAX_EDIT_DISTANCE: f64 = 8.0;
+
+// how far along logging in a client is
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ClientState {
+    Connected,
+    // handshake went through, waiting on login
+    Handshaken,
+    Joined,
+    // told to go away, dropped once that's been sent
+    Leaving,
+}
+
+struct RemoteClient {
+    address: SocketAddr,
+    conn: Connection,
+    state: ClientState,
+    name: String,
+    pose: Option<PlayerPose>,
+    moved: bool,
+    game_mode: GameMode,
+    horizontal_view: i32,
+    vertical_view: i32,
+    // chunks the client has a copy of, edits in these get passed on
+    sent: HashSet<IVec3>,
+    // chunks still to send, farthest first so the closest pops off the end
+    wanted: Vec<IVec3>,
+    // the chunk and view distances wanted was worked out for
+    wanted_for: Option<(IVec3, i32, i32)>,
+}
+
+impl RemoteClient {
+    fn chunk_position(&self) -> Option<IVec3> {
+        let pose = self.pose?;
+        Some(UniverseTransform::from_dim_xyz(pose.dimension, pose.position).get_chunk_position())
+    }
+
+    fn in_reach(&self, pos: IVec3) -> bool {
+        self.pose.is_some_and(|pose| pose.position.distance(pos.as_dvec3() + 0.5) <= MAX_EDIT_DISTANCE)
+    }
+
+    fn kick(&mut self, id: u32, reason: &str) {
+        info!("Kicking player {} ({}): {}", id, self.address, reason);
+        self.conn.send(&ServerMessage::Disconnect { reason: String::from(reason) });
+        self.state = ClientState::Leaving;
+    }
+}
+
+fn in_view(center: IVec3, chunk: IVec3, horizontal: i32, vertical: i32) -> bool {
+    let d = (chunk - center).abs();
+    d.x.max(d.z) <= horizontal && d.y <= vertical
+}
+
+#[derive(Resource)]
+pub struct Server {
+    listener: TcpListener,
+    clients: HashMap<u32, RemoteClient>,
+    next_id: u32,
+    // what everyone joins in
+    pub game_mode: GameMode,
+}
+
+impl Server {
+    pub fn bind(address: &str) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, clients: HashMap::new(), next_id: 0, game_mode: GameMode::default() })
+    }
+
+    // where it's really listening, for when it was bound to port 0
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+// block edits that went through this tick, sent out at the end of it
+#[derive(Resource, Default)]
+struct PendingChanges(Vec<(IVec3, BlockId)>);
+
+fn accept_clients(mut server: ResMut<Server>) {
+    loop {
+        let (stream, address) = match server.listener.accept() {
+            Ok(accepted) => accepted,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => break,
+            Err(e) => {
+                warn!("Couldn't accept a connection: {}", e);
+                break;
+            }
+        };
+        let conn = match Connection::new(stream) {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Couldn't set up the connection from {}: {}", address, e);
+                continue;
+            }
+        };
+
+        let id = server.next_id;
+        server.next_id += 1;
+        let game_mode = server.game_mode;
+        info!("{} connected as player {}", address, id);
+        server.clients.insert(id, RemoteClient {
+            address,
+            conn,
+            state: ClientState::Connected,
+            name: String::new(),
+            pose: None,
+            moved: false,
+            game_mode,
+            horizontal_view: 0,
+            vertical_view: 0,
+            sent: HashSet::new(),
+            wanted: Vec::new(),
+            wanted_for: None,
+        });
+    }
+}
+
+fn apply_edit(universe: &Universe, id: u32, client: &mut RemoteClient, pos: IVec3, block: BlockId, changes: &mut PendingChanges) {
+    // chunks the client hasn't been sent aren't theirs to edit, and this way nothing gets generated for an edit
+    let allowed = universe.is_known_block(block)
+        && client.sent.contains(&split_block_position(pos).0)
+        && client.in_reach(pos)
+        && client.game_mode.can_edit_blocks();
+    if allowed {
+        if let Some(old) = universe.set_block(pos, block, EditCause::RemotePlayer(id)) {
+            if old != block {
+                changes.0.push((pos, block));
+            }
+            return;
+        }
+    }
+    // only the client that asked needs telling, so it undoes whatever it guessed would happen
+    if let Some(actual) = universe.block_at_int(pos) {
+        client.conn.send(&ServerMessage::BlockChange { pos, block: actual });
+    }
+}
+
+// edits the server made itself, like fluids flowing, get passed on the same way as players' edits
+fn queue_own_edits(mut ev_changed: EventReader<BlockChangedEvent>, mut changes: ResMut<PendingChanges>) {
+    for BlockChangedEvent(edit) in ev_changed.read() {
+        if !matches!(edit.cause, EditCause::RemotePlayer(_)) {
+            changes.0.push((edit.pos, edit.new));
+        }
+    }
+}
+
+fn receive_messages(
+    mut server: ResMut<Server>,
+    universe: Res<Universe>,
+    mut changes: ResMut<PendingChanges>,
+    mut gone: Local<Vec<u32>>,
+) {
+    for (id, client) in server.clients.iter_mut() {
+        let messages = match client.conn.receive::<ClientMessage>() {
+            Ok(messages) => messages,
+            Err(e) => {
+                info!("Player {} ({}) disconnected: {}", id, client.address, e);
+                gone.push(*id);
+                continue;
+            }
+        };
+
+        for message in messages {
+            if client.state == ClientState::Leaving {
+                break;
+            }
+            match (client.state, message) {
+                (_, ClientMessage::Disconnect { reason }) => {
+                    info!("Player {} ({}, {}) left: {}", id, client.name, client.address, reason);
+                    gone.push(*id);
+                    break;
+                }
+                (ClientState::Connected, ClientMessage::Handshake { version }) => {
+                    if version == PROTOCOL_VERSION {
+                        client.state = ClientState::Handshaken;
+                    } else {
+                        let reason = format!("Your protocol version is {}, this server's is {}", version, PROTOCOL_VERSION);
+                        client.kick(*id, &reason);
+                    }
+                }
+                (ClientState::Handshaken, ClientMessage::Login { name }) => {
+                    info!("Player {} logged in as {}", id, name);
+                    client.name = name;
+                    client.state = ClientState::Joined;
+                    let blocks = universe.registered_blocks()
+                        .into_iter()
+                        .map(|(base, data)| (data.name.clone(), base, data.state_count()))
+                        .collect();
+                    client.conn.send(&ServerMessage::LoginSuccess { id: *id, seed: universe.seed, game_mode: client.game_mode, blocks });
+                }
+                (ClientState::Joined, ClientMessage::PlayerMove { pose, horizontal_view, vertical_view }) => {
+                    client.moved |= client.pose != Some(pose);
+                    client.pose = Some(pose);
+                    client.horizontal_view = (horizontal_view as i32).min(MAX_HORIZONTAL_VIEW);
+                    client.vertical_view = (vertical_view as i32).min(MAX_VERTICAL_VIEW);
+                }
+                (ClientState::Joined, ClientMessage::BlockChange { pos, block }) => {
+                    apply_edit(&universe, *id, client, pos, block, &mut changes);
+                }
+                (_, message) => client.kick(*id, &format!("Didn't expect {:?} right now", message)),
+            }
+        }
+    }
+    drop_clients(&mut server, &mut gone);
+}
+
+fn drop_clients(server: &mut Server, gone: &mut Vec<u32>) {
+    for id in gone.drain(..) {
+        if server.clients.remove(&id).is_none() {
+            continue;
+        }
+        for client in server.clients.values_mut() {
+            client.conn.send(&ServerMessage::PlayerLeft { id });
+        }
+    }
+}
+
+fn broadcast_changes(mut server: ResMut<Server>, mut changes: ResMut<PendingChanges>) {
+    if !changes.0.is_empty() {
+        let mut by_chunk: HashMap<IVec3, Vec<(UVec3, BlockId)>> = HashMap::new();
+        for (pos, block) in changes.0.drain(..) {
+            let (chunk, local) = split_block_position(pos);
+            by_chunk.entry(chunk).or_default().push((local, block));
+        }
+
+        for client in server.clients.values_mut() {
+            for (chunk, edits) in by_chunk.iter().filter(|(c, _)| client.sent.contains(c)) {
+                if let [(local, block)] = edits.as_slice() {
+                    let pos = *chunk * CHUNK_SIZE_I32 + local.as_ivec3();
+                    client.conn.send(&ServerMessage::BlockChange { pos, block: *block });
+                    continue;
+                }
+                for part in edits.chunks(MAX_CHANGES_PER_MESSAGE) {
+                    client.conn.send(&ServerMessage::MultiBlockChange { chunk: *chunk, changes: part.to_vec() });
+                }
+            }
+        }
+    }
+
+    let moved: Vec<(u32, PlayerPose)> = server.clients.iter_mut()
+        .filter(|(_, c)| c.moved)
+        .filter_map(|(id, c)| {
+            c.moved = false;
+            Some((*id, c.pose?))
+        })
+        .collect();
+    for (id, client) in server.clients.iter_mut().filter(|(_, c)| c.state == ClientState::Joined) {
+        for (mover, pose) in &moved {
+            if mover != id {
+                client.conn.send(&ServerMessage::PlayerMove { id: *mover, pose: *pose });
+            }
+        }
+    }
+}
+
+fn stream_chunks(mut server: ResMut<Server>, universe: Res<Universe>) {
+    for client in server.clients.values_mut() {
+        let Some(center) = client.chunk_position() else {
+            continue; // don't know where they are yet
+        };
+        let (h, v) = (client.horizontal_view, client.vertical_view);
+        if client.wanted_for != Some((center, h, v)) {
+            client.wanted_for = Some((center, h, v));
+            // forget chunks well out of range, so they get sent fresh if the player comes back
+            client.sent.retain(|c| in_view(center, *c, h + 1, v + 1));
+            client.wanted = Region::from_corners(center - IVec3::new(h, v, h), center + IVec3::new(h, v, h))
+                .iter()
+                .filter(|c| !client.sent.contains(c))
+                .collect();
+            // closest first, same as chunk loading on the client
+            client.wanted.sort_by_key(|c| std::cmp::Reverse((*c - center).abs().max_element()));
+        }
+        if client.conn.backlog() > MAX_BACKLOG {
+            continue;
+        }
+
+        for _ in 0..CHUNKS_PER_TICK {
+            let Some(coords) = client.wanted.pop() else {
+                break;
+            };
+            let stored = universe.fetch_chunk(&coords).and_then(|bytes| Chunk::read_from(bytes.as_ref()));
+            let chunk = match stored {
+                Some(chunk) => chunk,
+                None => {
+                    let chunk = generate_chunk(&universe, coords);
+                    universe.flush_chunk(&coords, &chunk);
+                    chunk
+                }
+            };
+            client.conn.send(&ServerMessage::ChunkData { coords, chunk: Box::new(chunk) });
+            client.sent.insert(coords);
+        }
+    }
+}
+
+fn flush_connections(mut server: ResMut<Server>, mut gone: Local<Vec<u32>>) {
+    for (id, client) in server.clients.iter_mut() {
+        if let Err(e) = client.conn.flush() {
+            info!("Player {} ({}) disconnected: {}", id, client.address, e);
+            gone.push(*id);
+        } else if client.state == ClientState::Leaving {
+            // the disconnect message has been handed to the socket, that's all they get
+            gone.push(*id);
+        }
+    }
+    drop_clients(&mut server, &mut gone);
+}
+
+// expects a Server and a Universe to be inserted by whoever adds this, and the BlockEventsPlugin
+pub struct ServerPlugin;
+impl Plugin for ServerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(TICK_RATE))
+            .init_resource::<PendingChanges>()
+            .add_systems(FixedUpdate, (
+                accept_clients,
+                receive_messages,
+                queue_own_edits,
+                broadcast_changes,
+                stream_chunks,
+                flush_connections,
+            ).chain());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::position::universe_location::UniverseLocation;
+use crate::position::universe_transform::UniverseTransform;
+use crate::world::fluid::fluid_state;
+use crate::world::universe::Universe;
+use bevy::math::f64::DVec3;
+use bevy::prelude::*;
+
+pub const GRAVITY: f64 = 28.0;
+pub const TERMINAL_VELOCITY: f64 = 60.0;
+pub const JUMP_VELOCITY: f64 = 9.0;
+// ledges at most this tall are walked up without jumping
+pub const STEP_HEIGHT: f64 = 0.6;
+
+// fully under a fluid this pushes up a little harder than gravity pulls down, so things float up to the surface
+pub const BUOYANCY: f64 = 30.0;
+// fraction of velocity lost per second while in a fluid
+pub const FLUID_DRAG: f64 = 2.0;
+
+// keeps boxes that are exactly touching a face from counting as overlapping
+const EPSILON: f64 = 1e-7;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Aabb {
+    pub fn block(pos: IVec3) -> Self {
+        let min = pos.as_dvec3();
+        Aabb { min, max: min + DVec3::ONE }
+    }
+
+    pub fn offset(&self, by: DVec3) -> Self {
+        Aabb { min: self.min + by, max: self.max + by }
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..3).all(|a| self.max[a] > other.min[a] + EPSILON && self.min[a] < other.max[a] - EPSILON)
+    }
+
+    // grows the box in the direction of the movement along one axis
+    fn stretched(&self, axis: usize, delta: f64) -> Self {
+        let mut out = *self;
+        if delta > 0.0 {
+            out.max[axis] += delta;
+        } else {
+            out.min[axis] += delta;
+        }
+        out
+    }
+
+    // every block position this box overlaps
+    pub fn blocks(&self) -> impl Iterator<Item = IVec3> {
+        let lo = (self.min + DVec3::splat(EPSILON)).floor().as_ivec3();
+        let hi = (self.max - DVec3::splat(EPSILON)).floor().as_ivec3();
+        (lo.x..=hi.x).flat_map(move |x| {
+            (lo.y..=hi.y).flat_map(move |y| {
+                (lo.z..=hi.z).map(move |z| IVec3::new(x, y, z))
+            })
+        })
+    }
+}
+
+// the box an entity occupies, relative to its UniverseTransform
+// the transform sits at eye level, horizontally centered in the box
+#[derive(Component, Clone, Copy)]
+pub struct Collider {
+    pub width: f64,
+    pub height: f64,
+    pub eye_height: f64,
+}
+
+impl Collider {
+    pub const PLAYER: Collider = Collider {
+        width: 0.6,
+        height: 1.8,
+        eye_height: 1.62,
+    };
+
+    pub fn aabb_at(&self, eye: DVec3) -> Aabb {
+        let half = self.width / 2.0;
+        let feet = eye - DVec3::Y * self.eye_height;
+        Aabb {
+            min: feet - DVec3::new(half, 0.0, half),
+            max: feet + DVec3::new(half, self.height, half),
+        }
+    }
+}
+
+#[derive(Component, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MovementMode {
+    // gravity and collision
+    Walking,
+    // collision but no gravity
+    #[default]
+    Flying,
+    // free movement through everything
+    Noclip,
+}
+
+#[derive(Component, Default)]
+pub struct PhysicsBody {
+    pub velocity: DVec3,
+    pub on_ground: bool,
+    // how much of the body's height is in a fluid, 0 to 1
+    pub submerged: f64,
+}
+
+// the collision boxes of the block at a position
+// unloaded chunks are solid so nothing falls out of the world while it's loading
+pub fn block_collision_boxes(universe: &Universe, dimension: u32, pos: IVec3) -> Vec<Aabb> {
+    let loc = UniverseLocation::from_dim_xyz(dimension, pos.as_dvec3());
+    match universe.block_at(loc) {
+        None => vec![Aabb::block(pos)],
+        Some(id) if universe.get_block_data_id(id).block_type.is_solid() => {
+            let corner = pos.as_dvec3();
+            universe.block_boxes(id).iter()
+                .map(|b| Aabb { min: corner + b.min.as_dvec3(), max: corner + b.max.as_dvec3() })
+                .collect()
+        }
+        Some(_) => vec![],
+    }
+}
+
+// how much of a box's height is under the surface of a fluid, going by the column its center is in
+pub fn submerged_fraction(universe: &Universe, dimension: u32, aabb: &Aabb) -> f64 {
+    let center = (aabb.min + aabb.max) / 2.0;
+    let height = aabb.max.y - aabb.min.y;
+    let mut under = 0.0;
+    for y in aabb.min.y.floor() as i32..=aabb.max.y.floor() as i32 {
+        let pos = IVec3::new(center.x.floor() as i32, y, center.z.floor() as i32);
+        let loc = UniverseLocation::from_dim_xyz(dimension, pos.as_dvec3());
+        let Some(fluid) = universe.block_at(loc).and_then(|id| fluid_state(universe, id)) else {
+            continue;
+        };
+        let surface = y as f64 + fluid.height() as f64;
+        under += (surface.min(aabb.max.y) - (y as f64).max(aabb.min.y)).max(0.0);
+    }
+    (under / height).min(1.0)
+}
+
+// how far a box can move along one axis before hitting a block, at most delta
+fn sweep_axis(universe: &Universe, dimension: u32, aabb: &Aabb, axis: usize, delta: f64) -> f64 {
+    if delta == 0.0 {
+        return 0.0;
+    }
+
+    let mut allowed = delta;
+    for pos in aabb.stretched(axis, delta).blocks() {
+        for b in block_collision_boxes(universe, dimension, pos) {
+            // only boxes that overlap on the other two axes can be hit
+            let overlaps = (0..3)
+                .filter(|a| *a != axis)
+                .all(|a| b.max[a] > aabb.min[a] + EPSILON && b.min[a] < aabb.max[a] - EPSILON);
+            if !overlaps {
+                continue;
+            }
+
+            if delta > 0.0 && b.min[axis] >= aabb.max[axis] - EPSILON {
+                allowed = allowed.min((b.min[axis] - aabb.max[axis]).max(0.0));
+            } else if delta < 0.0 && b.max[axis] <= aabb.min[axis] + EPSILON {
+                allowed = allowed.max((b.max[axis] - aabb.min[axis]).min(0.0));
+            }
+        }
+    }
+    allowed
+}
+
+// moves a box by delta one axis at a time (Y first), stopping at blocks
+// returns the movement that actually happened
+fn move_and_collide(universe: &Universe, dimension: u32, aabb: Aabb, delta: DVec3) -> DVec3 {
+    let mut moved = DVec3::ZERO;
+    let mut current = aabb;
+    for axis in [1, 0, 2] {
+        let d = sweep_axis(universe, dimension, &current, axis, delta[axis]);
+        moved[axis] = d;
+        let mut step = DVec3::ZERO;
+        step[axis] = d;
+        current = current.offset(step);
+    }
+    moved
+}
+
+// runs in FixedUpdate, so Time here is the fixed tick time
+pub fn apply_physics(
+    time: Res<Time>,
+    universe: Res<Universe>,
+    mut query: Query<(&mut UniverseTransform, &mut PhysicsBody, &Collider, &MovementMode)>,
+) {
+    let dt = time.delta_seconds_f64();
+
+    for (mut trans, mut body, collider, mode) in query.iter_mut() {
+        if *mode == MovementMode::Noclip {
+            body.velocity = DVec3::ZERO;
+            body.on_ground = false;
+            continue;
+        }
+
+        let walking = *mode == MovementMode::Walking;
+        let dimension = trans.loc.dimension;
+        let aabb = collider.aabb_at(trans.loc.position);
+        body.submerged = submerged_fraction(&universe, dimension, &aabb);
+        if walking {
+            let lift = BUOYANCY * body.submerged;
+            body.velocity.y = (body.velocity.y - (GRAVITY - lift) * dt).max(-TERMINAL_VELOCITY);
+            if body.submerged > 0.0 {
+                body.velocity *= (1.0 - FLUID_DRAG * dt).max(0.0);
+            }
+        }
+        let wanted = body.velocity * dt;
+        let mut moved = move_and_collide(&universe, dimension, aabb, wanted);
+
+        // blocked horizontally while standing on something: try going up, over and back down
+        let blocked = (moved.x - wanted.x).abs() > EPSILON || (moved.z - wanted.z).abs() > EPSILON;
+        if blocked && walking && body.on_ground {
+            let up = move_and_collide(&universe, dimension, aabb, DVec3::Y * STEP_HEIGHT);
+            let over = move_and_collide(&universe, dimension, aabb.offset(up), DVec3::new(wanted.x, 0.0, wanted.z));
+            let down = move_and_collide(&universe, dimension, aabb.offset(up + over), DVec3::NEG_Y * up.y);
+            let stepped = up + over + down;
+            if stepped.x.powi(2) + stepped.z.powi(2) > moved.x.powi(2) + moved.z.powi(2) {
+                moved = stepped;
+            }
+        }
+
+        body.on_ground = wanted.y < 0.0 && moved.y > wanted.y + EPSILON;
+        if (moved.y - wanted.y).abs() > EPSILON {
+            body.velocity.y = 0.0;
+        }
+        if (moved.x - wanted.x).abs() > EPSILON {
+            body.velocity.x = 0.0;
+        }
+        if (moved.z - wanted.z).abs() > EPSILON {
+            body.velocity.z = 0.0;
+        }
+
+        *trans += moved;
+    }
+}
+
+pub struct PhysicsPlugin;
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, apply_physics);
+    }
+}
@@ -1,17 +1,29 @@
-use std::iter;
-
-use crate::chunk::chunk::{BlockId, Chunk, AIR};
+use crate::chunk::chunk::{split_block_position, AIR};
+use crate::inventory::Inventory;
+use crate::physics::{apply_physics, Aabb, Collider, MovementMode, PhysicsBody, JUMP_VELOCITY};
+use crate::settings::input::{Action, ActionInput};
+use crate::settings::menu::{not_paused, PauseMenu};
 use crate::settings::Settings;
-use crate::universe_transform::UniverseTransform;
-use crate::world::loading::ChunkRemeshEvent;
+use crate::position::universe_transform::UniverseTransform;
+use crate::position::universe_location::UniverseLocation;
+use crate::position::interpolation::{interpolate_positions, rendered_position, TickInterpolation};
+use crate::world::edits::EditCause;
 use crate::world::universe::Universe;
+use bevy::app::RunFixedMainLoop;
 use bevy::input::mouse::MouseMotion;
 use bevy::math::f64::DVec3;
 use bevy::prelude::*;
 use bevy::render::view::{GpuCulling, NoCpuCulling};
+use bevy::time::common_conditions::on_timer;
 use bevy::window::CursorGrabMode;
-use zerocopy::FromBytes;
-use itertools::Itertools;
+use bevy_egui::EguiContexts;
+use std::time::Duration;
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
+
+pub mod gamemode;
+pub mod model;
+use gamemode::GameMode;
+use model::PlayerModelPlugin;
 
 
 #[derive(Default, Component)]
@@ -20,10 +32,35 @@ pub struct Player;
 #[derive(Component)]
 pub struct ThisPlayer;
 
-#[derive(Default, Bundle)]
+#[derive(Bundle)]
 pub struct PlayerBundle {
     _p: Player,
     world_position: UniverseTransform,
+    movement_mode: MovementMode,
+    body: PhysicsBody,
+    collider: Collider,
+    input: MovementInput,
+    interpolation: TickInterpolation,
+    inventory: Inventory,
+    game_mode: GameMode,
+    breaking: BlockBreaking,
+}
+
+impl Default for PlayerBundle {
+    fn default() -> Self {
+        PlayerBundle {
+            _p: Player,
+            world_position: UniverseTransform::default(),
+            movement_mode: MovementMode::default(),
+            body: PhysicsBody::default(),
+            collider: Collider::PLAYER,
+            input: MovementInput::default(),
+            interpolation: TickInterpolation::default(),
+            inventory: Inventory::default(),
+            game_mode: GameMode::default(),
+            breaking: BlockBreaking::default(),
+        }
+    }
 }
 
 fn init_this_player(mut commands: Commands) {
@@ -34,10 +71,11 @@ fn init_this_player(mut commands: Commands) {
 
     let mut world_position = UniverseTransform::from_dim_xyz(0, (0.0, 100.0, 12.0));
     world_position.pitch = 1.57;
-    let player_bundle = PlayerBundle {
-        _p: Player,
-        world_position: world_position,
+    let mut player_bundle = PlayerBundle {
+        world_position: world_position.clone(),
+        ..default()
     };
+    player_bundle.interpolation.snap_to(world_position.loc.position);
     commands.spawn((camera_bundle, player_bundle, ThisPlayer,
             // Enable GPU frustum culling (does not automatically disable CPU frustum culling).
             GpuCulling,
@@ -46,72 +84,219 @@ fn init_this_player(mut commands: Commands) {
     ));
 }
 
+// everything about a player that is kept between sessions
+// stored as raw bytes in the world database, so changing this layout makes old saves unreadable (they get ignored)
+#[derive(AsBytes, FromBytes, FromZeroes)]
+#[repr(C)]
+struct PlayerRecord {
+    position: [f64; 3],
+    pitch: f64,
+    yaw: f64,
+    dimension: u32,
+    // GameMode as a number, this used to be padding so older saves come back in creative
+    game_mode: u32,
+    inventory: Inventory,
+}
+
+// there's only ever one local player for now
+pub const LOCAL_PLAYER_NAME: &str = "local";
+
+fn load_this_player(
+    universe: Res<Universe>,
+    mut player: Query<(&mut UniverseTransform, &mut TickInterpolation, &mut Inventory, &mut GameMode), With<ThisPlayer>>,
+) {
+    let Some(bytes) = universe.load_player(LOCAL_PLAYER_NAME) else {
+        return; // first time in this world
+    };
+    let Some(record) = PlayerRecord::read_from(bytes.as_ref()) else {
+        warn!("Saved player data is from an incompatible version, starting fresh");
+        return;
+    };
+
+    let (mut trans, mut interp, mut inventory, mut game_mode) = player.single_mut();
+    trans.loc = UniverseLocation::from_dim_xyz(record.dimension, record.position);
+    trans.pitch = record.pitch;
+    trans.yaw = record.yaw;
+    interp.snap_to(trans.loc.position);
+    *inventory = record.inventory;
+    *game_mode = GameMode::from_u32(record.game_mode).unwrap_or_else(|| {
+        warn!("Unknown saved game mode {}, using the default", record.game_mode);
+        GameMode::default()
+    });
+}
+
+fn save_this_player(
+    universe: Res<Universe>,
+    player: Query<(&UniverseTransform, &Inventory, &GameMode), With<ThisPlayer>>,
+) {
+    let (trans, inventory, game_mode) = player.single();
+    let record = PlayerRecord {
+        position: trans.loc.position.to_array(),
+        pitch: trans.pitch,
+        yaw: trans.yaw,
+        dimension: trans.loc.dimension,
+        game_mode: *game_mode as u32,
+        inventory: inventory.clone(),
+    };
+    universe.save_player(LOCAL_PLAYER_NAME, record.as_bytes());
+}
+
 fn mouse_lock_handler(
-    keys: Res<ButtonInput<KeyCode>>,
-    mouse: Res<ButtonInput<MouseButton>>,
+    input: ActionInput,
+    mut egui: EguiContexts,
+    pause_menu: Res<PauseMenu>,
     mut window_query: Query<&mut Window>,
 ) {
     // handle mouse locking
-    // todo: force mouse to middle of screen when in locked mode,
-    // be nice with the eguis
+    // todo: force mouse to middle of screen when in locked mode
     let mut window = window_query.single_mut();
-    if mouse.just_pressed(MouseButton::Left) {
+    // clicking on a menu shouldn't grab the mouse
+    let over_ui = egui.ctx_mut().is_pointer_over_area();
+    if input.just_pressed(Action::CaptureCursor) && !pause_menu.open && !over_ui {
         window.cursor.visible = false;
         window.cursor.grab_mode = CursorGrabMode::Locked;
     }
-    if keys.just_pressed(KeyCode::Escape) {
+    if input.just_pressed(Action::Pause) {
         window.cursor.visible = true;
         window.cursor.grab_mode = CursorGrabMode::None;
     }
 }
 
+// all in blocks per second
+pub const WALK_SPEED : f64 = 4.3;
+pub const SPRINT_SPEED : f64 = 5.6;
+pub const CROUCH_SPEED : f64 = 1.3;
+pub const FLY_SPEED : f64 = 6.0;
+pub const FLY_SPRINT_SPEED : f64 = 15.0;
+pub const SWIM_SPEED : f64 = 2.2;
+// holding jump in a fluid swims up at this speed
+pub const SWIM_UP_VELOCITY : f64 = 3.5;
+
+// what the player wants to do this tick
+// read from the keyboard every frame, and acted on in FixedUpdate
+#[derive(Component, Default)]
+pub struct MovementInput {
+    pub direction: DVec3,
+    pub sprint: bool,
+    pub jump: bool,
+    pub crouch: bool,
+}
+
 fn camera_mover(
-    keys: Res<ButtonInput<KeyCode>>,
-    mut query: Query<&mut UniverseTransform, With<ThisPlayer>>,
+    actions: ActionInput,
+    mut query: Query<(&UniverseTransform, &mut MovementInput, &MovementMode), With<ThisPlayer>>,
 ) {
     // handle direction
-    let mut worldpos = query.single_mut();
+    let (worldpos, mut input, mode) = query.single_mut();
     let mut direction = DVec3::ZERO;
 
-    if keys.pressed(KeyCode::KeyW) {
+    if actions.pressed(Action::MoveForward) {
         direction += worldpos.forward();
     }
-    if keys.pressed(KeyCode::KeyS) {
+    if actions.pressed(Action::MoveBackward) {
         direction += worldpos.backward();
     }
-    if keys.pressed(KeyCode::KeyA) {
+    if actions.pressed(Action::MoveLeft) {
         direction += worldpos.left();
     }
-    if keys.pressed(KeyCode::KeyD) {
+    if actions.pressed(Action::MoveRight) {
         direction += worldpos.right();
     }
-    if keys.pressed(KeyCode::Space) {
-        direction += DVec3::Y;
-    }
-    if keys.pressed(KeyCode::ShiftLeft) {
-        direction -= DVec3::Y;
-    }
+    let stick = actions.movement_stick().as_dvec2();
+    direction += stick.y * worldpos.forward() + stick.x * worldpos.right();
 
-    if direction == DVec3::ZERO {
-        return;
+    // when walking, jump jumps instead of going up
+    if *mode != MovementMode::Walking {
+        if actions.pressed(Action::Jump) {
+            direction += DVec3::Y;
+        }
+        if actions.pressed(Action::Descend) {
+            direction -= DVec3::Y;
+        }
     }
 
-    let speed = if keys.pressed(KeyCode::ControlLeft) {
-        0.25
-    } else {
-        0.1
-    };
+    // a stick only part of the way over moves slower, but nothing goes faster than full speed
+    input.direction = direction.clamp_length_max(1.0);
+    input.sprint = actions.pressed(Action::Sprint);
+    input.jump = actions.pressed(Action::Jump);
+    // descend does this instead when walking
+    input.crouch = *mode == MovementMode::Walking && actions.pressed(Action::Descend);
+}
 
-    *worldpos += speed * direction.normalize();
+fn apply_movement_input(
+    time: Res<Time>,
+    mut query: Query<(&mut UniverseTransform, &mut PhysicsBody, &MovementInput, &MovementMode)>,
+) {
+    for (mut worldpos, mut body, input, mode) in query.iter_mut() {
+        match mode {
+            MovementMode::Walking => {
+                // physics does the actual moving, we just tell it where we want to go
+                let swimming = body.submerged > 0.0 && !body.on_ground;
+                let speed = if input.crouch {
+                    CROUCH_SPEED
+                } else if swimming {
+                    SWIM_SPEED
+                } else if input.sprint {
+                    SPRINT_SPEED
+                } else {
+                    WALK_SPEED
+                };
+                body.velocity.x = speed * input.direction.x;
+                body.velocity.z = speed * input.direction.z;
+
+                if input.jump && body.on_ground {
+                    body.velocity.y = JUMP_VELOCITY;
+                } else if input.jump && body.submerged > 0.0 {
+                    body.velocity.y = body.velocity.y.max(SWIM_UP_VELOCITY);
+                }
+            }
+            MovementMode::Flying => {
+                // no gravity, but still bumps into things
+                let speed = if input.sprint { FLY_SPRINT_SPEED } else { FLY_SPEED };
+                body.velocity = speed * input.direction;
+            }
+            MovementMode::Noclip => {
+                let speed = if input.sprint { FLY_SPRINT_SPEED } else { FLY_SPEED };
+                *worldpos += speed * time.delta_seconds_f64() * input.direction;
+            }
+        }
+    }
+}
+
+fn toggle_movement_mode(
+    input: ActionInput,
+    mut query: Query<(&mut MovementMode, &GameMode), With<ThisPlayer>>,
+) {
+    if input.just_pressed(Action::ToggleFlight) {
+        let (mut mode, game_mode) = query.single_mut();
+        if !game_mode.can_fly() {
+            return;
+        }
+        *mode = match *mode {
+            MovementMode::Walking => MovementMode::Flying,
+            MovementMode::Flying | MovementMode::Noclip => MovementMode::Walking,
+        };
+        info!("Movement mode: {:?}", *mode);
+    }
 }
 
 fn camera_rotator(
     mut camera_query: Query<&mut UniverseTransform, With<ThisPlayer>>,
     mut mouse_motion_event_reader: EventReader<MouseMotion>,
     mut window_query: Query<&mut Window>,
+    actions: ActionInput,
+    time: Res<Time<Real>>,
     settings: Res<Settings>
 ) {
     let mut worldpos = camera_query.single_mut();
+
+    // the stick works without grabbing the cursor, the mouse doesn't
+    let look = actions.look_stick() * settings.gamepad_look_sensitivity * time.delta_seconds();
+    if look != Vec2::ZERO {
+        worldpos.add_pitch_clamp(look.y as f64);
+        worldpos.add_yaw(look.x as f64);
+    }
+
     let window = window_query.single_mut();
     if window.cursor.grab_mode == CursorGrabMode::None {
         return;
@@ -129,82 +314,130 @@ fn camera_rotator(
 
 
 pub const PLAYER_REACH : f64 = 5.0;
+
+// how far along breaking a block the player is, outside of creative
+#[derive(Component, Default)]
+pub struct BlockBreaking {
+    pub target: Option<IVec3>,
+    // 0 to 1, the block breaks at 1
+    pub progress: f32,
+}
+
 fn block_handler(
-    mouse: Res<ButtonInput<MouseButton>>,
-    player: Query<&UniverseTransform, With<ThisPlayer>>,
+    time: Res<Time>,
+    input: ActionInput,
+    mut player: Query<(&UniverseTransform, &Collider, &mut Inventory, &mut BlockBreaking, &GameMode), With<ThisPlayer>>,
     universe: Res<Universe>,
-    mut ev_remesh: EventWriter<ChunkRemeshEvent>,
-    mut gizmos: Gizmos
+    mut gizmos: Gizmos,
+    origin: Res<RenderOrigin>,
 ) {
-    let worldpos = player.single();
-    
-    let mut target_block = None;
-    let mut adjacent_block = None;
-    let mut target_id = None;
-    for (prev, current) in iter::once(None).chain(
-        worldpos.integer_raycast(PLAYER_REACH).into_iter().map(|s| Some(s))
-    ).tuple_windows() {
-        match universe.block_at(current.expect("Values after the first guaranteed to be Some")) {
-            None => {break;} // hit an unloaded chunk, fail immediately
-            Some(AIR) => {} // hit an air block, keep going
-            Some(e) => { // hit a solid, record it and exit the raycast loop
-                target_block = current;
-                adjacent_block = prev;
-                target_id = Some(e);
-                break;
-            }
-        }
+    let (worldpos, collider, mut inventory, mut breaking, game_mode) = player.single_mut();
+    if !game_mode.can_interact() {
+        *breaking = BlockBreaking::default();
+        return;
     }
 
-    if let Some(p) = target_block {
-        gizmos.cuboid(
-            Transform::from_translation((p.position - worldpos.loc.position + (0.5 * DVec3::ONE)).as_vec3()), 
-            bevy::color::palettes::css::PURPLE
-        );
+    let Some((hit, target_id)) = universe.raycast_block(worldpos, PLAYER_REACH) else {
+        *breaking = BlockBreaking::default();
+        return;
+    };
+    let p = hit.block;
+    let center = (p.as_dvec3() - origin.0 + (0.5 * DVec3::ONE)).as_vec3();
 
-        if mouse.just_pressed(MouseButton::Left) {
-            info!("Broke block at {} {} {} (ID {})", p.position.x, p.position.y, p.position.z, target_id.unwrap().0);
+    gizmos.cuboid(
+        Transform::from_translation(center), 
+        bevy::color::palettes::css::PURPLE
+    );
 
-            let chunk_pos = &p.get_chunk_position();
-            let c = universe.fetch_chunk_exists(chunk_pos);
-            let mut chunk = Chunk::read_from(c.as_ref()).unwrap();
+    if !game_mode.can_edit_blocks() {
+        return;
+    }
 
-            let [x, y, z] = p.get_within_chunk_position().floor().to_array();
-            chunk.place(BlockId(0), (x as u32, y as u32, z as u32));
-            universe.flush_chunk(chunk_pos, &chunk);
-            ev_remesh.send(ChunkRemeshEvent(*chunk_pos));
-        }
+    if !input.pressed(Action::Break) || breaking.target != Some(p) {
+        *breaking = BlockBreaking::default();
     }
+    if input.pressed(Action::Break) {
+        let data = universe.get_block_data_id(target_id);
+        let broken = if game_mode.instant_break() {
+            input.just_pressed(Action::Break)
+        } else if data.is_breakable() {
+            breaking.target = Some(p);
+            breaking.progress += time.delta_seconds() / data.hardness;
+            breaking.progress >= 1.0
+        } else {
+            false
+        };
+
+        if breaking.progress > 0.0 && !broken {
+            // grows to fill the block as it breaks
+            gizmos.cuboid(
+                Transform::from_translation(center).with_scale(Vec3::splat(breaking.progress)),
+                bevy::color::palettes::css::RED
+            );
+        }
 
-    if let Some(ap) = adjacent_block {
-        if mouse.just_pressed(MouseButton::Right) {
-            info!("Placed block at {} {} {}", ap.position.x, ap.position.y, ap.position.z);
-            
-            let chunk_pos = &ap.get_chunk_position();
-            let c = universe.fetch_chunk_exists(&ap.get_chunk_position());
-            let mut chunk = Chunk::read_from(c.as_ref()).unwrap();
+        if broken {
+            universe.set_block(p, AIR, EditCause::Player);
+            *breaking = BlockBreaking::default();
 
-            let [x, y, z] = ap.get_within_chunk_position().floor().to_array();
-            chunk.place(BlockId(1), (x as u32, y as u32, z as u32));
-            universe.flush_chunk(chunk_pos, &chunk);
-            ev_remesh.send(ChunkRemeshEvent(*chunk_pos));
+            if !game_mode.unlimited_blocks() {
+                let dropped = data.drop.as_deref().and_then(|name| universe.try_block_id_from_name(name));
+                if let Some(id) = dropped {
+                    // if there's no room the block is just lost
+                    inventory.add(id, 1);
+                }
+            }
         }
     }
 
+    // placing goes against the face we're looking at
+    if let Some(ap) = hit.adjacent() {
+        if input.just_pressed(Action::Place) {
+            // don't get stuck inside what we just placed
+            if game_mode.has_collision() && Aabb::block(ap).intersects(&collider.aabb_at(worldpos.loc.position)) {
+                return;
+            }
+            let (chunk_pos, _) = split_block_position(ap);
+            // the face can be on the border of a chunk that hasn't loaded yet
+            if !universe.chunk_generated(&chunk_pos) {
+                return;
+            }
+            let block = if game_mode.unlimited_blocks() {
+                inventory.selected_block()
+            } else {
+                inventory.take_selected()
+            };
+            let Some(block) = block else {
+                return;
+            };
+            let block = universe.placement_state(block, worldpos, &hit);
+            universe.set_block(ap, block, EditCause::Player);
+        }
+    }
+}
 
+// where the camera is drawn this frame, everything rendered is positioned relative to this
+// this is the player's interpolated position, which can lag slightly behind its UniverseTransform
+#[derive(Resource, Default)]
+pub struct RenderOrigin(pub DVec3);
 
+fn update_render_origin(
+    mut origin: ResMut<RenderOrigin>,
+    player: Query<(&UniverseTransform, Option<&TickInterpolation>), With<ThisPlayer>>,
+) {
+    let (trans, interp) = player.single();
+    origin.0 = rendered_position(trans, interp);
 }
 
 // shift any entity with both a Transform and a UniverseTransform to be relative to the player
 // TODO move this into the positions module
 pub fn translate_all_world_transforms(
-    mut to_move: Query<(&mut Transform, &UniverseTransform)>,
-    player: Query<&UniverseTransform, With<ThisPlayer>>,
+    mut to_move: Query<(&mut Transform, &UniverseTransform, Option<&TickInterpolation>)>,
+    origin: Res<RenderOrigin>,
 ) {
-    let player_world_position = player.single();
-
-    for (transform, world_position) in to_move.iter_mut() {
-        world_position.to_render_transform(player_world_position, transform.into_inner());
+    for (transform, world_position, interp) in to_move.iter_mut() {
+        let position = rendered_position(world_position, interp);
+        world_position.to_render_transform(position, origin.0, transform.into_inner());
     }
 }
 
@@ -230,10 +463,18 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, init_this_player)
+        app.add_plugins(PlayerModelPlugin)
+            .add_systems(Startup, (init_this_player, load_this_player).chain())
             .add_systems(Startup, spawn_reticle)
-            .add_systems(Update, (mouse_lock_handler, camera_mover, camera_rotator))
-            .add_systems(Update, block_handler)
-            .add_systems(PostUpdate, translate_all_world_transforms);
+            .init_resource::<RenderOrigin>()
+            .add_systems(Update, (mouse_lock_handler, toggle_movement_mode, camera_mover))
+            .add_systems(Update, camera_rotator.run_if(not_paused))
+            .add_systems(FixedUpdate, apply_movement_input.before(apply_physics))
+            .add_systems(RunFixedMainLoop, update_render_origin.after(interpolate_positions))
+            .add_systems(Update, block_handler.run_if(not_paused))
+            .add_systems(PostUpdate, translate_all_world_transforms)
+            // save every so often in case of crashes, and when quitting
+            .add_systems(Update, save_this_player.run_if(on_timer(Duration::from_secs(30))))
+            .add_systems(Last, save_this_player.run_if(on_event::<AppExit>()));
     }
 }
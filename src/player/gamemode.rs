@@ -0,0 +1,45 @@
+use crate::physics::MovementMode;
+use crate::settings::input::{Action, ActionInput};
+use bevy::prelude::*;
+
+use super::ThisPlayer;
+
+// the server needs to know what players are allowed to do too, so the mode itself lives outside the client
+pub use crate::gamemode::GameMode;
+
+// keeps the movement mode within what the game mode allows
+pub fn apply_game_mode(mut query: Query<(&GameMode, &mut MovementMode), Changed<GameMode>>) {
+    for (game_mode, mut movement) in query.iter_mut() {
+        let allowed = if !game_mode.has_collision() {
+            MovementMode::Noclip
+        } else if !game_mode.can_fly() {
+            MovementMode::Walking
+        } else if *movement == MovementMode::Noclip {
+            MovementMode::Flying
+        } else {
+            *movement
+        };
+        // don't trip change detection for nothing
+        if *movement != allowed {
+            *movement = allowed;
+        }
+    }
+}
+
+fn cycle_game_mode(
+    input: ActionInput,
+    mut query: Query<&mut GameMode, With<ThisPlayer>>,
+) {
+    if input.just_pressed(Action::CycleGameMode) {
+        let mut mode = query.single_mut();
+        *mode = mode.next();
+        info!("Game mode: {}", mode.name());
+    }
+}
+
+pub struct GameModePlugin;
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (cycle_game_mode, apply_game_mode).chain());
+    }
+}
@@ -0,0 +1,355 @@
+// the character model drawn for every Player, and the third person camera that lets you see your own
+// the model is its own entity rather than a child of the player, since the local player's entity is the camera
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::animation::Animation;
+use bevy::gltf::Gltf;
+use bevy::math::f64::DVec3;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+use super::gamemode::GameMode;
+use super::{translate_all_world_transforms, MovementInput, Player, RenderOrigin, ThisPlayer, WALK_SPEED};
+use crate::physics::Collider;
+use crate::position::interpolation::{rendered_position, TickInterpolation};
+use crate::position::raycast::VoxelRaycast;
+use crate::position::universe_transform::UniverseTransform;
+use crate::settings::input::{Action, ActionInput};
+use crate::settings::menu::not_paused;
+use crate::world::universe::Universe;
+
+const PLAYER_MODEL: &str = "models/player.glb";
+// the bone turned to follow the player's pitch
+const HEAD_BONE: &str = "Head";
+
+// how long one click keeps the arm swinging, holding break keeps it going
+const SWING_TIME: f32 = 0.35;
+// below this many blocks per second the player counts as standing still
+const WALK_THRESHOLD: f64 = 0.3;
+const BLEND_TIME: Duration = Duration::from_millis(200);
+
+const THIRD_PERSON_DISTANCE: f64 = 4.0;
+// keeps the camera from clipping into the block it was pulled in by
+const CAMERA_MARGIN: f64 = 0.2;
+
+// the animations the model needs, looked up by name in the glTF file
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Clip {
+    Idle,
+    Walk,
+    Swing,
+    Crouch,
+}
+
+impl Clip {
+    const ALL: [Clip; 4] = [Clip::Idle, Clip::Walk, Clip::Swing, Clip::Crouch];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Clip::Idle => "idle",
+            Clip::Walk => "walk",
+            Clip::Swing => "swing",
+            Clip::Crouch => "crouch",
+        }
+    }
+}
+
+#[derive(Resource)]
+struct PlayerModelAssets {
+    gltf: Handle<Gltf>,
+    scene: Handle<Scene>,
+    // built once the glTF has loaded and its animations can be found
+    graph: Option<Handle<AnimationGraph>>,
+    clips: HashMap<Clip, AnimationNodeIndex>,
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraView {
+    #[default]
+    FirstPerson,
+    ThirdPerson,
+}
+
+// on a player, the entity its model was spawned as
+#[derive(Component)]
+pub struct PlayerModel(pub Entity);
+
+// on a model, the player it belongs to, and the parts of the spawned scene that get moved around
+#[derive(Component)]
+struct ModelOf {
+    player: Entity,
+    animator: Option<Entity>,
+    // along with the head's rotation before any looking up or down
+    head: Option<(Entity, Quat)>,
+}
+
+// what a player is doing, as far as animation cares
+#[derive(Component, Default)]
+pub struct PlayerAnimation {
+    last_position: Option<DVec3>,
+    // horizontal, blocks per second, smoothed over a few frames
+    speed: f64,
+    swing: f32,
+    playing: Option<Clip>,
+}
+
+fn load_player_model(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(PlayerModelAssets {
+        gltf: asset_server.load(PLAYER_MODEL),
+        scene: asset_server.load(GltfAssetLabel::Scene(0).from_asset(PLAYER_MODEL)),
+        graph: None,
+        clips: HashMap::new(),
+    });
+}
+
+fn build_animation_graph(
+    mut assets: ResMut<PlayerModelAssets>,
+    gltfs: Res<Assets<Gltf>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+) {
+    if assets.graph.is_some() {
+        return;
+    }
+    let Some(gltf) = gltfs.get(&assets.gltf) else {
+        return;
+    };
+
+    let mut graph = AnimationGraph::new();
+    for clip in Clip::ALL {
+        match gltf.named_animations.get(clip.name()) {
+            Some(handle) => {
+                let node = graph.add_clip(handle.clone(), 1.0, graph.root);
+                assets.clips.insert(clip, node);
+            }
+            None => warn!("{} has no \"{}\" animation", PLAYER_MODEL, clip.name()),
+        }
+    }
+    assets.graph = Some(graphs.add(graph));
+}
+
+fn attach_player_models(
+    assets: Res<PlayerModelAssets>,
+    players: Query<Entity, (With<Player>, Without<PlayerModel>)>,
+    mut commands: Commands,
+) {
+    for player in &players {
+        let model = commands.spawn((
+            SceneBundle { scene: assets.scene.clone(), ..default() },
+            ModelOf { player, animator: None, head: None },
+        )).id();
+        commands.entity(player).insert((PlayerModel(model), PlayerAnimation::default()));
+    }
+}
+
+// players can go away (like when someone leaves a server), their models go with them
+fn remove_orphaned_models(
+    models: Query<(Entity, &ModelOf)>,
+    players: Query<(), With<Player>>,
+    mut commands: Commands,
+) {
+    for (model, of) in &models {
+        if players.get(of.player).is_err() {
+            commands.entity(model).despawn_recursive();
+        }
+    }
+}
+
+// the scene spawns its own AnimationPlayer somewhere inside it, this finds it and hands it the graph
+fn link_animators(
+    assets: Res<PlayerModelAssets>,
+    animators: Query<Entity, (With<AnimationPlayer>, Without<Handle<AnimationGraph>>)>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    names: Query<&Name>,
+    transforms: Query<&Transform>,
+    mut models: Query<&mut ModelOf>,
+    mut commands: Commands,
+) {
+    let Some(graph) = &assets.graph else {
+        return;
+    };
+
+    for animator in &animators {
+        let Some(root) = parents.iter_ancestors(animator).find(|e| models.contains(*e)) else {
+            continue; // some other scene's
+        };
+        let mut model = models.get_mut(root).unwrap();
+        model.animator = Some(animator);
+        model.head = children.iter_descendants(root)
+            .find(|e| names.get(*e).is_ok_and(|n| n.as_str() == HEAD_BONE))
+            .and_then(|head| Some((head, transforms.get(head).ok()?.rotation)));
+        if model.head.is_none() {
+            warn!("{} has no \"{}\" bone, heads won't follow where players look", PLAYER_MODEL, HEAD_BONE);
+        }
+
+        commands.entity(animator).insert((graph.clone(), AnimationTransitions::new()));
+    }
+}
+
+fn track_player_motion(
+    time: Res<Time>,
+    mut players: Query<(&UniverseTransform, &mut PlayerAnimation)>,
+) {
+    let dt = time.delta_seconds_f64();
+    if dt == 0.0 {
+        return;
+    }
+    for (trans, mut anim) in &mut players {
+        let position = trans.loc.position;
+        // remote players only come with a position, so speed is worked out from that for everyone
+        let moved = anim.last_position.map_or(0.0, |last| (position - last).with_y(0.0).length());
+        anim.speed += (moved / dt - anim.speed) * (dt * 10.0).min(1.0);
+        anim.last_position = Some(position);
+        anim.swing = (anim.swing - dt as f32).max(0.0);
+    }
+}
+
+fn swing_on_interact(
+    input: ActionInput,
+    mut player: Query<(&mut PlayerAnimation, &GameMode), With<ThisPlayer>>,
+) {
+    let Ok((mut anim, game_mode)) = player.get_single_mut() else {
+        return;
+    };
+    if game_mode.can_interact() && (input.pressed(Action::Break) || input.just_pressed(Action::Place)) {
+        anim.swing = SWING_TIME;
+    }
+}
+
+fn drive_animations(
+    assets: Res<PlayerModelAssets>,
+    mut players: Query<(&mut PlayerAnimation, &PlayerModel, Option<&MovementInput>)>,
+    models: Query<&ModelOf>,
+    mut animators: Query<(&mut AnimationPlayer, &mut AnimationTransitions)>,
+) {
+    for (mut anim, PlayerModel(model), input) in &mut players {
+        let Some(animator) = models.get(*model).ok().and_then(|m| m.animator) else {
+            continue; // not spawned yet
+        };
+        let Ok((mut player, mut transitions)) = animators.get_mut(animator) else {
+            continue;
+        };
+
+        let crouching = input.is_some_and(|i| i.crouch);
+        let wanted = if anim.swing > 0.0 {
+            Clip::Swing
+        } else if crouching {
+            Clip::Crouch
+        } else if anim.speed > WALK_THRESHOLD {
+            Clip::Walk
+        } else {
+            Clip::Idle
+        };
+        // a model missing some animations makes do with idle
+        let (clip, node) = match assets.clips.get(&wanted) {
+            Some(node) => (wanted, *node),
+            None => match assets.clips.get(&Clip::Idle) {
+                Some(node) => (Clip::Idle, *node),
+                None => continue,
+            },
+        };
+
+        if anim.playing != Some(clip) {
+            transitions.play(&mut player, node, BLEND_TIME).repeat();
+            anim.playing = Some(clip);
+        }
+        if clip == Clip::Walk {
+            // feet keep up with the ground whether walking or sprinting
+            if let Some(active) = player.animation_mut(node) {
+                active.set_speed((anim.speed / WALK_SPEED) as f32);
+            }
+        }
+    }
+}
+
+fn position_player_models(
+    origin: Res<RenderOrigin>,
+    view: Res<CameraView>,
+    players: Query<(&UniverseTransform, Option<&TickInterpolation>, Option<&Collider>, Has<ThisPlayer>)>,
+    mut models: Query<(&ModelOf, &mut Transform, &mut Visibility)>,
+) {
+    for (model, mut transform, mut visibility) in &mut models {
+        let Ok((trans, interp, collider, local)) = players.get(model.player) else {
+            continue;
+        };
+        // the model's origin is at its feet, the player's is at eye level
+        let eye_height = collider.unwrap_or(&Collider::PLAYER).eye_height;
+        let feet = rendered_position(trans, interp) - DVec3::Y * eye_height;
+        transform.translation = (feet - origin.0).as_vec3();
+        // models face +Z
+        let forward = trans.forward();
+        transform.rotation = Quat::from_rotation_y(forward.x.atan2(forward.z) as f32);
+
+        *visibility = if local && *view == CameraView::FirstPerson {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+    }
+}
+
+// after animations are applied, or they'd just put the head back
+fn aim_heads(
+    players: Query<&UniverseTransform>,
+    models: Query<&ModelOf>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for model in &models {
+        let (Some((head, rest)), Ok(trans)) = (model.head, players.get(model.player)) else {
+            continue;
+        };
+        if let Ok(mut transform) = transforms.get_mut(head) {
+            // positive pitch is looking up, which tips +Z up when turned the other way around X
+            transform.rotation = rest * Quat::from_rotation_x(-trans.pitch as f32);
+        }
+    }
+}
+
+fn toggle_camera_view(input: ActionInput, mut view: ResMut<CameraView>) {
+    if input.just_pressed(Action::ToggleCameraView) {
+        *view = match *view {
+            CameraView::FirstPerson => CameraView::ThirdPerson,
+            CameraView::ThirdPerson => CameraView::FirstPerson,
+        };
+    }
+}
+
+// pulls the camera back behind the player, or less far if there's a wall in the way
+fn third_person_camera(
+    view: Res<CameraView>,
+    universe: Res<Universe>,
+    origin: Res<RenderOrigin>,
+    mut camera: Query<(&UniverseTransform, &mut Transform), With<ThisPlayer>>,
+) {
+    if *view != CameraView::ThirdPerson {
+        return;
+    }
+    let (trans, mut transform) = camera.single_mut();
+    let back = -trans.facing_direction();
+    let distance = universe.raycast_block_along(VoxelRaycast::new(origin.0, back, THIRD_PERSON_DISTANCE))
+        .map_or(THIRD_PERSON_DISTANCE, |(hit, _)| (hit.distance - CAMERA_MARGIN).max(0.0));
+    transform.translation += (back * distance).as_vec3();
+}
+
+pub struct PlayerModelPlugin;
+impl Plugin for PlayerModelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraView>()
+            .add_systems(Startup, load_player_model)
+            .add_systems(Update, (toggle_camera_view, swing_on_interact).run_if(not_paused))
+            .add_systems(Update, (
+                build_animation_graph,
+                attach_player_models,
+                remove_orphaned_models,
+                link_animators,
+                track_player_motion,
+                drive_animations,
+            ).chain().after(swing_on_interact))
+            .add_systems(PostUpdate, (position_player_models, third_person_camera)
+                .after(translate_all_world_transforms)
+                .before(TransformSystem::TransformPropagate))
+            .add_systems(PostUpdate, aim_heads.after(Animation).before(TransformSystem::TransformPropagate));
+    }
+}
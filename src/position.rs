@@ -1,3 +1,5 @@
 pub mod universe_location;
 pub mod universe_transform;
-pub mod universe_position_math;
\ No newline at end of file
+pub mod universe_position_math;
+pub mod interpolation;
+pub mod raycast;
@@ -0,0 +1,53 @@
+use bevy::{app::RunFixedMainLoop, math::f64::DVec3, prelude::*, time::run_fixed_main_schedule};
+
+use super::universe_transform::UniverseTransform;
+
+// entities simulated in FixedUpdate only move once per tick
+// this remembers where they were at the start of the latest tick so they can be drawn somewhere in between
+#[derive(Component, Default, Clone, Copy)]
+pub struct TickInterpolation {
+    previous: DVec3,
+    pub rendered: DVec3,
+}
+
+impl TickInterpolation {
+    // skip interpolating for this tick, for teleports and such
+    pub fn snap_to(&mut self, position: DVec3) {
+        self.previous = position;
+        self.rendered = position;
+    }
+}
+
+// where an entity should be drawn this frame
+pub fn rendered_position(trans: &UniverseTransform, interp: Option<&TickInterpolation>) -> DVec3 {
+    match interp {
+        Some(i) => i.rendered,
+        None => trans.loc.position,
+    }
+}
+
+fn store_previous_positions(mut query: Query<(&UniverseTransform, &mut TickInterpolation)>) {
+    for (trans, mut interp) in query.iter_mut() {
+        interp.previous = trans.loc.position;
+    }
+}
+
+pub fn interpolate_positions(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&UniverseTransform, &mut TickInterpolation)>,
+) {
+    // how far we are into the next tick that hasn't happened yet
+    let t = fixed_time.overstep_fraction_f64();
+    for (trans, mut interp) in query.iter_mut() {
+        interp.rendered = interp.previous.lerp(trans.loc.position, t);
+    }
+}
+
+pub struct InterpolationPlugin;
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedFirst, store_previous_positions)
+            // right after the fixed ticks for this frame, so everything in Update sees this frame's positions
+            .add_systems(RunFixedMainLoop, interpolate_positions.after(run_fixed_main_schedule));
+    }
+}
@@ -0,0 +1,257 @@
+use bevy::{math::f64::DVec3, prelude::*};
+
+// a block passed through by a ray
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaycastHit {
+    pub block: IVec3,
+    // points out of the face the ray entered through, zero for the block the ray starts in
+    pub normal: IVec3,
+    // how far along the ray the block was entered
+    pub distance: f64,
+    // exactly where the block was entered
+    pub point: DVec3,
+}
+
+impl RaycastHit {
+    // the block on the other side of the face that was hit, i.e. where a placed block goes
+    // None for the starting block, since it wasn't entered through any face
+    pub fn adjacent(&self) -> Option<IVec3> {
+        (self.normal != IVec3::ZERO).then(|| self.block + self.normal)
+    }
+}
+
+// walks every block a ray passes through, in order (Amanatides & Woo's voxel traversal)
+// when the ray passes exactly through an edge or corner, X is stepped before Y before Z,
+// so every pair of consecutive blocks always shares a face
+pub struct VoxelRaycast {
+    origin: DVec3,
+    direction: DVec3,
+    max_distance: f64,
+
+    block: IVec3,
+    step: IVec3,
+    // distance along the ray to the next boundary on each axis
+    t_max: DVec3,
+    // distance along the ray between boundaries on each axis
+    t_delta: DVec3,
+    started: bool,
+    finished: bool,
+}
+
+impl VoxelRaycast {
+    pub fn new(origin: DVec3, direction: DVec3, max_distance: f64) -> Self {
+        let direction = direction.normalize_or_zero();
+        let block = origin.floor().as_ivec3();
+        let step = direction.signum().as_ivec3();
+
+        let mut t_max = DVec3::INFINITY;
+        let mut t_delta = DVec3::INFINITY;
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                continue;
+            }
+            let boundary = if direction[axis] > 0.0 {
+                block[axis] as f64 + 1.0
+            } else {
+                block[axis] as f64
+            };
+            t_max[axis] = (boundary - origin[axis]) / direction[axis];
+            t_delta[axis] = 1.0 / direction[axis].abs();
+        }
+
+        VoxelRaycast {
+            origin,
+            direction,
+            max_distance: max_distance.max(0.0),
+            block,
+            step,
+            t_max,
+            t_delta,
+            started: false,
+            finished: direction == DVec3::ZERO,
+        }
+    }
+
+    pub fn origin(&self) -> DVec3 {
+        self.origin
+    }
+
+    // normalized
+    pub fn direction(&self) -> DVec3 {
+        self.direction
+    }
+}
+
+// where a ray enters a box, as the distance along the ray and the normal of the face it went through
+// a ray starting inside the box hits it at distance 0 with a zero normal, same as the first VoxelRaycast hit
+pub fn ray_box_hit(origin: DVec3, direction: DVec3, min: DVec3, max: DVec3) -> Option<(f64, IVec3)> {
+    let mut enter = 0.0;
+    let mut exit = f64::INFINITY;
+    let mut normal = IVec3::ZERO;
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let a = (min[axis] - origin[axis]) / direction[axis];
+        let b = (max[axis] - origin[axis]) / direction[axis];
+        let (near, far) = if a < b { (a, b) } else { (b, a) };
+        if near > enter {
+            enter = near;
+            normal = IVec3::ZERO;
+            normal[axis] = if direction[axis] > 0.0 { -1 } else { 1 };
+        }
+        exit = exit.min(far);
+        if enter > exit {
+            return None;
+        }
+    }
+    Some((enter, normal))
+}
+
+impl Iterator for VoxelRaycast {
+    type Item = RaycastHit;
+
+    fn next(&mut self) -> Option<RaycastHit> {
+        // we always see the block we are currently in
+        if !self.started {
+            self.started = true;
+            return Some(RaycastHit {
+                block: self.block,
+                normal: IVec3::ZERO,
+                distance: 0.0,
+                point: self.origin,
+            });
+        }
+        if self.finished {
+            return None;
+        }
+
+        // cross whichever boundary comes first, ties go to the lowest axis
+        let mut axis = 0;
+        for a in [1, 2] {
+            if self.t_max[a] < self.t_max[axis] {
+                axis = a;
+            }
+        }
+
+        let distance = self.t_max[axis];
+        if distance > self.max_distance || !distance.is_finite() {
+            self.finished = true;
+            return None;
+        }
+
+        self.block[axis] += self.step[axis];
+        self.t_max[axis] += self.t_delta[axis];
+
+        let mut normal = IVec3::ZERO;
+        normal[axis] = -self.step[axis];
+
+        let mut point = self.origin + self.direction * distance;
+        // float error can leave the point a hair off the face it's supposed to be on
+        point[axis] = if self.step[axis] > 0 {
+            self.block[axis] as f64
+        } else {
+            self.block[axis] as f64 + 1.0
+        };
+
+        Some(RaycastHit {
+            block: self.block,
+            normal,
+            distance,
+            point,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::universe_transform::UniverseTransform;
+    use proptest::prelude::*;
+
+    const EPSILON: f64 = 1e-6;
+
+    fn transform(origin: DVec3, pitch: f64, yaw: f64) -> UniverseTransform {
+        let mut t = UniverseTransform::from_dim_xyz(0, origin);
+        t.pitch = pitch;
+        t.yaw = yaw;
+        t
+    }
+
+    // the two raycasts only disagree on which block comes first when the ray goes exactly through an edge or corner,
+    // or when a boundary is right at the end of the range
+    fn has_tie(hits: &[RaycastHit], max_distance: f64) -> bool {
+        hits.iter().skip(1).any(|hit| {
+            let crossed = (0..3).find(|a| hit.normal[*a] != 0).unwrap();
+            (0..3).filter(|a| *a != crossed).any(|a| (hit.point[a] - hit.point[a].round()).abs() < EPSILON)
+                || (hit.distance - max_distance).abs() < EPSILON
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn visits_the_same_blocks_as_integer_raycast(
+            x in -100.0..100.0f64, y in -100.0..100.0f64, z in -100.0..100.0f64,
+            pitch in -1.5..1.5f64, yaw in 0.0..std::f64::consts::TAU,
+            range in 4.0..64.0f64,
+        ) {
+            let t = transform(DVec3::new(x, y, z), pitch, yaw);
+            let hits: Vec<RaycastHit> = t.voxel_raycast(range).collect();
+            let next_boundary = t.voxel_raycast(range * 2.0).nth(hits.len()).map(|h| h.distance);
+            prop_assume!(!has_tie(&hits, range));
+            prop_assume!(next_boundary.is_none_or(|d| (d - range).abs() > EPSILON));
+
+            let dda: Vec<IVec3> = hits.iter().map(|h| h.block).collect();
+            let old: Vec<IVec3> = t.integer_raycast(range).iter().map(|l| l.position.as_ivec3()).collect();
+            prop_assert_eq!(dda, old);
+        }
+
+        #[test]
+        fn hits_agree_with_themselves(
+            x in -100.0..100.0f64, y in -100.0..100.0f64, z in -100.0..100.0f64,
+            dx in -1.0..1.0f64, dy in -1.0..1.0f64, dz in -1.0..1.0f64,
+            range in 0.0..64.0f64,
+        ) {
+            let origin = DVec3::new(x, y, z);
+            let direction = DVec3::new(dx, dy, dz).normalize_or_zero();
+            prop_assume!(direction != DVec3::ZERO);
+            let hits: Vec<RaycastHit> = VoxelRaycast::new(origin, direction, range).collect();
+
+            let first = hits[0];
+            prop_assert_eq!(first.block, origin.floor().as_ivec3());
+            prop_assert_eq!(first.normal, IVec3::ZERO);
+            prop_assert_eq!(first.distance, 0.0);
+            prop_assert_eq!(first.point, origin);
+
+            for pair in hits.windows(2) {
+                let (previous, hit) = (pair[0], pair[1]);
+                prop_assert_eq!(hit.normal.abs().element_sum(), 1);
+                prop_assert_eq!(hit.block + hit.normal, previous.block);
+                prop_assert!(hit.distance >= previous.distance && hit.distance <= range);
+                prop_assert!(hit.point.distance(origin + direction * hit.distance) < EPSILON);
+
+                // the point is on the face it says it came through
+                let axis = (0..3).find(|a| hit.normal[*a] != 0).unwrap();
+                let face = if hit.normal[axis] < 0 { hit.block[axis] } else { hit.block[axis] + 1 };
+                prop_assert_eq!(hit.point[axis], face as f64);
+                for a in 0..3 {
+                    prop_assert!(hit.point[a] >= hit.block[a] as f64 - EPSILON);
+                    prop_assert!(hit.point[a] <= hit.block[a] as f64 + 1.0 + EPSILON);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn straight_down_the_x_axis() {
+        let hits: Vec<RaycastHit> = VoxelRaycast::new(DVec3::new(0.5, 0.5, 0.5), DVec3::X, 2.6).collect();
+        let blocks: Vec<IVec3> = hits.iter().map(|h| h.block).collect();
+        assert_eq!(blocks, vec![IVec3::ZERO, IVec3::X, IVec3::new(2, 0, 0), IVec3::new(3, 0, 0)]);
+        assert!(hits.iter().skip(1).all(|h| h.normal == IVec3::NEG_X));
+        assert_eq!(hits[1].distance, 0.5);
+        assert_eq!(hits[1].point, DVec3::new(1.0, 0.5, 0.5));
+    }
+}
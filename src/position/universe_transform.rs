@@ -2,6 +2,7 @@ use bevy::{math::f64::DVec3, prelude::*};
 use bevy_math::{CompassOctant, CompassQuadrant, DQuat};
 
 use super::universe_location::*;
+use super::raycast::VoxelRaycast;
 
 #[derive(Default, Debug, Component, Clone)]
 pub struct UniverseTransform {
@@ -87,12 +88,13 @@ impl UniverseTransform {
         (Dir2::new(Vec2::new(self.forward().z as f32, self.forward().x as f32))).unwrap().into()
     }
 
-    // set the given transform to match this WorldPosition, relative to origin
+    // set the given transform to match this WorldPosition, drawn at position relative to origin
     // used in rendering to allow 64 bit floats to be used for physics
-    pub fn to_render_transform(&self, origin: &Self, out: &mut Transform) {
-        let x = (self.loc.position.x - origin.loc.position.x) as f32;
-        let y = (self.loc.position.y - origin.loc.position.y) as f32;
-        let z = (self.loc.position.z - origin.loc.position.z) as f32;
+    // position is passed separately since it may be interpolated between ticks, see interpolation.rs
+    pub fn to_render_transform(&self, position: DVec3, origin: DVec3, out: &mut Transform) {
+        let x = (position.x - origin.x) as f32;
+        let y = (position.y - origin.y) as f32;
+        let z = (position.z - origin.z) as f32;
 
         out.translation = Vec3::new(x, y, z);
         // axis is -Y instead of Y to get the rotation to not be backwards
@@ -115,7 +117,14 @@ impl UniverseTransform {
         self.loc.get_within_chunk_position()      
     }
 
+    // every block looked at by this transform, in order, up to max_range away
+    // also reports which face each block was entered through, see raycast.rs
+    pub fn voxel_raycast(&self, max_range: f64) -> VoxelRaycast {
+        VoxelRaycast::new(self.loc.position, self.facing_direction(), max_range)
+    }
+
     // gets all integer coordinates directly looked at by this worldtransform, in order, up to max_range 
+    // superseded by voxel_raycast, kept around for the debug overlay to compare against
     // TODO: once rust '24 drops, make this return an iterator instead of adding things to hits
     pub fn integer_raycast(&self, max_range: f64) -> Vec<UniverseLocation> {
         let mut hits = vec![];
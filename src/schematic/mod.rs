@@ -0,0 +1,124 @@
+pub mod nbt;
+pub mod sponge;
+pub mod vox;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use crate::chunk::chunk::{BlockId, AIR};
+use crate::world::region::Clipboard;
+use crate::world::universe::Universe;
+
+// the mapping used when none is given, covers vanilla minecraft blocks we have equivalents for
+pub const DEFAULT_MAPPING: &str = include_str!("../../assets/schematics/mapping.txt");
+
+// translates between block names in other games' formats and our block registry
+// the table format is one `external = ours` pair per line, with # comments
+// `* = ours` sets what unmapped external blocks turn into (air if not given)
+#[derive(Default)]
+pub struct BlockMapping {
+    to_ours: HashMap<String, String>,
+    to_external: HashMap<String, String>,
+    fallback: Option<String>,
+}
+
+impl BlockMapping {
+    pub fn parse(table: &str) -> io::Result<Self> {
+        let mut mapping = BlockMapping::default();
+        for (n, line) in table.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((external, ours)) = line.split_once('=') else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {} of block mapping is missing an '='", n + 1),
+                ));
+            };
+            let (external, ours) = (external.trim().to_string(), ours.trim().to_string());
+
+            if external == "*" {
+                mapping.fallback = Some(ours);
+                continue;
+            }
+            // several external blocks can map onto one of ours, the first one listed is used when exporting
+            mapping.to_external.entry(ours.clone()).or_insert(external.clone());
+            mapping.to_ours.insert(external, ours);
+        }
+        Ok(mapping)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    // finds our block for an external name, trying the exact name, then the name without block states
+    // (sponge palettes look like `minecraft:oak_log[axis=y]`), then the name without a namespace
+    pub fn to_ours(&self, universe: &Universe, external: &str) -> BlockId {
+        let stateless = external.split('[').next().unwrap_or(external);
+        let unnamespaced = stateless.rsplit(':').next().unwrap_or(stateless);
+
+        let mapped = [external, stateless].iter().find_map(|n| self.to_ours.get(*n));
+        if let Some(id) = mapped.and_then(|name| universe.try_block_id_from_name(name)) {
+            return id;
+        }
+        if let Some(id) = universe.try_block_id_from_name(unnamespaced) {
+            return id;
+        }
+        self.fallback
+            .as_deref()
+            .and_then(|f| universe.try_block_id_from_name(f))
+            .unwrap_or(AIR)
+    }
+
+    // finds the external name for one of our blocks, or namespaces our own name if it has no mapping
+    pub fn to_external(&self, ours: &str) -> String {
+        match self.to_external.get(ours) {
+            Some(external) => external.clone(),
+            None => format!("dirlaku:{}", ours),
+        }
+    }
+
+    pub fn external_for(&self, ours: &str) -> Option<&str> {
+        self.to_external.get(ours).map(|s| s.as_str())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchematicFormat {
+    Sponge,
+    MagicaVoxel,
+}
+
+impl SchematicFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "schem" => Some(SchematicFormat::Sponge),
+            "vox" => Some(SchematicFormat::MagicaVoxel),
+            _ => None,
+        }
+    }
+}
+
+pub fn import_file(path: &Path, universe: &Universe, mapping: &BlockMapping) -> io::Result<Clipboard> {
+    let format = SchematicFormat::from_path(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown schematic extension, expected .schem or .vox"))?;
+    let reader = BufReader::new(File::open(path)?);
+    match format {
+        SchematicFormat::Sponge => sponge::read_sponge(reader, universe, mapping),
+        SchematicFormat::MagicaVoxel => vox::read_vox(reader, universe, mapping),
+    }
+}
+
+pub fn export_file(path: &Path, universe: &Universe, clipboard: &Clipboard, mapping: &BlockMapping) -> io::Result<()> {
+    let format = SchematicFormat::from_path(path)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown schematic extension, expected .schem or .vox"))?;
+    let writer = BufWriter::new(File::create(path)?);
+    match format {
+        SchematicFormat::Sponge => sponge::write_sponge(writer, universe, clipboard, mapping),
+        SchematicFormat::MagicaVoxel => vox::write_vox(writer, universe, clipboard, mapping),
+    }
+}
@@ -0,0 +1,200 @@
+// just enough of minecraft's NBT format to read and write sponge schematics
+// reading never panics on bad input, it just returns an InvalidData error
+
+use std::io::{self, Read, Write};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+// compounds nested deeper than this are rejected instead of blowing the stack
+const MAX_DEPTH: usize = 512;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match self {
+            Tag::Byte(_) => 1,
+            Tag::Short(_) => 2,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::Float(_) => 5,
+            Tag::Double(_) => 6,
+            Tag::ByteArray(_) => 7,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::IntArray(_) => 11,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    // looks up a child of a compound by name
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.iter().find(|(n, _)| n == name).map(|(_, t)| t),
+            _ => None,
+        }
+    }
+
+    // NBT has no unsigned types, so schematic dimensions are stored as shorts and read back as unsigned
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(v) => Some(*v as i64),
+            Tag::Short(v) => Some(*v as u16 as i64),
+            Tag::Int(v) => Some(*v as i64),
+            Tag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+// reads a length prefix, making sure it isn't negative
+fn read_len<R: Read>(r: &mut R) -> io::Result<usize> {
+    let len = r.read_i32::<BigEndian>()?;
+    usize::try_from(len).map_err(|_| invalid(format!("negative length {}", len)))
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    let len = r.read_u16::<BigEndian>()? as usize;
+    let mut buf = vec![0; len];
+    r.read_exact(&mut buf)?;
+    // java's modified utf-8 only differs for nulls and surrogates, which shouldn't show up in block names
+    String::from_utf8(buf).map_err(|_| invalid("string is not valid utf-8"))
+}
+
+// reads len elements one at a time, so a bogus length fails at the end of the input
+// instead of trying to allocate gigabytes up front
+fn read_array<R: Read, T>(r: &mut R, mut element: impl FnMut(&mut R) -> io::Result<T>) -> io::Result<Vec<T>> {
+    let len = read_len(r)?;
+    let mut out = Vec::with_capacity(len.min(1 << 16));
+    for _ in 0..len {
+        out.push(element(r)?);
+    }
+    Ok(out)
+}
+
+fn read_payload<R: Read>(r: &mut R, id: u8, depth: usize) -> io::Result<Tag> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("NBT nested too deeply"));
+    }
+
+    Ok(match id {
+        1 => Tag::Byte(r.read_i8()?),
+        2 => Tag::Short(r.read_i16::<BigEndian>()?),
+        3 => Tag::Int(r.read_i32::<BigEndian>()?),
+        4 => Tag::Long(r.read_i64::<BigEndian>()?),
+        5 => Tag::Float(r.read_f32::<BigEndian>()?),
+        6 => Tag::Double(r.read_f64::<BigEndian>()?),
+        7 => Tag::ByteArray(read_array(r, |r| r.read_i8())?),
+        8 => Tag::String(read_string(r)?),
+        9 => {
+            let element_id = r.read_u8()?;
+            let len = read_len(r)?;
+            if element_id == 0 && len > 0 {
+                return Err(invalid("list of end tags"));
+            }
+            let mut out = Vec::with_capacity(len.min(1 << 12));
+            for _ in 0..len {
+                out.push(read_payload(r, element_id, depth + 1)?);
+            }
+            Tag::List(out)
+        }
+        10 => {
+            let mut entries = vec![];
+            loop {
+                let child_id = r.read_u8()?;
+                if child_id == 0 {
+                    break;
+                }
+                let name = read_string(r)?;
+                entries.push((name, read_payload(r, child_id, depth + 1)?));
+            }
+            Tag::Compound(entries)
+        }
+        11 => Tag::IntArray(read_array(r, |r| r.read_i32::<BigEndian>())?),
+        12 => Tag::LongArray(read_array(r, |r| r.read_i64::<BigEndian>())?),
+        _ => return Err(invalid(format!("unknown tag id {}", id))),
+    })
+}
+
+// reads a named root tag, returning its name and value
+pub fn read_root<R: Read>(r: &mut R) -> io::Result<(String, Tag)> {
+    let id = r.read_u8()?;
+    if id != 10 {
+        return Err(invalid("root tag is not a compound"));
+    }
+    let name = read_string(r)?;
+    Ok((name, read_payload(r, id, 0)?))
+}
+
+fn write_string<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    let len = u16::try_from(s.len()).map_err(|_| invalid("string too long for NBT"))?;
+    w.write_u16::<BigEndian>(len)?;
+    w.write_all(s.as_bytes())
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize) -> io::Result<()> {
+    let len = i32::try_from(len).map_err(|_| invalid("array too long for NBT"))?;
+    w.write_i32::<BigEndian>(len)
+}
+
+fn write_payload<W: Write>(w: &mut W, tag: &Tag) -> io::Result<()> {
+    match tag {
+        Tag::Byte(v) => w.write_i8(*v),
+        Tag::Short(v) => w.write_i16::<BigEndian>(*v),
+        Tag::Int(v) => w.write_i32::<BigEndian>(*v),
+        Tag::Long(v) => w.write_i64::<BigEndian>(*v),
+        Tag::Float(v) => w.write_f32::<BigEndian>(*v),
+        Tag::Double(v) => w.write_f64::<BigEndian>(*v),
+        Tag::ByteArray(v) => {
+            write_len(w, v.len())?;
+            v.iter().try_for_each(|b| w.write_i8(*b))
+        }
+        Tag::String(s) => write_string(w, s),
+        Tag::List(v) => {
+            // empty lists are written as lists of end tags, like minecraft does
+            w.write_u8(v.first().map_or(0, |t| t.id()))?;
+            write_len(w, v.len())?;
+            v.iter().try_for_each(|t| write_payload(w, t))
+        }
+        Tag::Compound(entries) => {
+            for (name, child) in entries {
+                w.write_u8(child.id())?;
+                write_string(w, name)?;
+                write_payload(w, child)?;
+            }
+            w.write_u8(0)
+        }
+        Tag::IntArray(v) => {
+            write_len(w, v.len())?;
+            v.iter().try_for_each(|i| w.write_i32::<BigEndian>(*i))
+        }
+        Tag::LongArray(v) => {
+            write_len(w, v.len())?;
+            v.iter().try_for_each(|i| w.write_i64::<BigEndian>(*i))
+        }
+    }
+}
+
+pub fn write_root<W: Write>(w: &mut W, name: &str, tag: &Tag) -> io::Result<()> {
+    w.write_u8(tag.id())?;
+    write_string(w, name)?;
+    write_payload(w, tag)
+}
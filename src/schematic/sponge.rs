@@ -0,0 +1,150 @@
+// sponge schematic format (https://github.com/SpongePowered/Schematic-Specification)
+// we write version 2, and read versions 2 and 3
+
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+use bevy::prelude::IVec3;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::nbt::{read_root, write_root, Tag};
+use super::BlockMapping;
+use crate::chunk::chunk::BlockId;
+use crate::world::region::Clipboard;
+use crate::world::universe::Universe;
+
+// minecraft 1.20.1, the version everything is expected to be able to read
+const DATA_VERSION: i32 = 3465;
+
+// anything bigger than this is almost certainly a corrupt file, not a real build
+const MAX_VOLUME: usize = 1 << 28;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn write_varint(out: &mut Vec<i8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte as i8);
+            return;
+        }
+        out.push((byte | 0x80) as i8);
+    }
+}
+
+fn read_varints(data: &[i8], count: usize) -> io::Result<Vec<u32>> {
+    let mut out = Vec::with_capacity(count);
+    let mut bytes = data.iter().map(|b| *b as u8);
+    for _ in 0..count {
+        let mut value = 0u32;
+        let mut shift = 0;
+        loop {
+            let byte = bytes.next().ok_or_else(|| invalid("block data ended early"))?;
+            value |= ((byte & 0x7F) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+            if shift > 28 {
+                return Err(invalid("varint in block data is too long"));
+            }
+        }
+        out.push(value);
+    }
+    Ok(out)
+}
+
+pub fn write_sponge<W: Write>(w: W, universe: &Universe, clipboard: &Clipboard, mapping: &BlockMapping) -> io::Result<()> {
+    let size = clipboard.size;
+    if size.max_element() > u16::MAX as i32 {
+        return Err(invalid("region is too big for a sponge schematic"));
+    }
+
+    // palette indices are handed out in order of first appearance
+    let mut palette: HashMap<BlockId, u32> = HashMap::new();
+    let mut palette_tag = vec![];
+    let mut block_data = Vec::with_capacity(clipboard.blocks.len());
+    // our clipboard uses the same x, z, y ordering as the format, so no reshuffling is needed
+    for block in &clipboard.blocks {
+        let index = *palette.entry(*block).or_insert_with(|| {
+            let index = palette_tag.len() as u32;
+            let name = mapping.to_external(&universe.get_block_data_id(*block).name);
+            palette_tag.push((name, Tag::Int(index as i32)));
+            index
+        });
+        write_varint(&mut block_data, index);
+    }
+
+    let root = Tag::Compound(vec![
+        (String::from("Version"), Tag::Int(2)),
+        (String::from("DataVersion"), Tag::Int(DATA_VERSION)),
+        (String::from("Width"), Tag::Short(size.x as u16 as i16)),
+        (String::from("Height"), Tag::Short(size.y as u16 as i16)),
+        (String::from("Length"), Tag::Short(size.z as u16 as i16)),
+        (String::from("Offset"), Tag::IntArray(vec![0, 0, 0])),
+        (String::from("PaletteMax"), Tag::Int(palette_tag.len() as i32)),
+        (String::from("Palette"), Tag::Compound(palette_tag)),
+        (String::from("BlockData"), Tag::ByteArray(block_data)),
+        (String::from("BlockEntities"), Tag::List(vec![])),
+    ]);
+
+    let mut encoder = GzEncoder::new(w, Compression::default());
+    write_root(&mut encoder, "Schematic", &root)?;
+    encoder.finish()?.flush()
+}
+
+pub fn read_sponge<R: Read>(r: R, universe: &Universe, mapping: &BlockMapping) -> io::Result<Clipboard> {
+    let (_, root) = read_root(&mut GzDecoder::new(r))?;
+    // version 3 wraps everything in another "Schematic" compound
+    let root = match root.get("Schematic") {
+        Some(inner) => inner.clone(),
+        None => root,
+    };
+
+    let dim = |name: &str| {
+        let value = root.get(name)
+            .and_then(Tag::as_int)
+            .ok_or_else(|| invalid(format!("schematic is missing {}", name)))?;
+        if (0..=u16::MAX as i64).contains(&value) {
+            Ok(value as i32)
+        } else {
+            Err(invalid(format!("schematic {} is out of range", name)))
+        }
+    };
+    let size = IVec3::new(dim("Width")?, dim("Height")?, dim("Length")?);
+    let volume = size.x as usize * size.y as usize * size.z as usize;
+    if volume > MAX_VOLUME {
+        return Err(invalid("schematic is unreasonably large"));
+    }
+
+    // version 3 moved the palette and data into a "Blocks" compound
+    let (palette, data) = match root.get("Blocks") {
+        Some(blocks) => (blocks.get("Palette"), blocks.get("Data")),
+        None => (root.get("Palette"), root.get("BlockData")),
+    };
+    let Some(Tag::Compound(palette)) = palette else {
+        return Err(invalid("schematic has no palette"));
+    };
+    let Some(Tag::ByteArray(data)) = data else {
+        return Err(invalid("schematic has no block data"));
+    };
+
+    let mut ids: HashMap<u32, BlockId> = HashMap::new();
+    for (name, index) in palette {
+        let index = index.as_int().ok_or_else(|| invalid("palette index is not a number"))?;
+        ids.insert(index as u32, mapping.to_ours(universe, name));
+    }
+
+    let mut clipboard = Clipboard::new(size);
+    for (i, index) in read_varints(data, volume)?.into_iter().enumerate() {
+        clipboard.blocks[i] = *ids
+            .get(&index)
+            .ok_or_else(|| invalid(format!("block data refers to missing palette entry {}", index)))?;
+    }
+    Ok(clipboard)
+}
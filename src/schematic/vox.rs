@@ -0,0 +1,193 @@
+// magicavoxel .vox format (https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt)
+// only the first model in a file is read, and only SIZE, XYZI and RGBA chunks are written
+// palette entries map onto our blocks through `vox:<index>` names in the block mapping
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read, Write};
+
+use bevy::prelude::IVec3;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use super::BlockMapping;
+use crate::chunk::chunk::{BlockId, AIR};
+use crate::world::region::Clipboard;
+use crate::world::universe::Universe;
+
+const VOX_VERSION: i32 = 150;
+const MAX_SIZE: i32 = 256;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+// magicavoxel is Z up, we are Y up. both are right handed, so one of the horizontal axes has to flip
+fn to_vox(pos: IVec3, size: IVec3) -> IVec3 {
+    IVec3::new(pos.x, size.z - 1 - pos.z, pos.y)
+}
+
+fn from_vox(pos: IVec3, size: IVec3) -> IVec3 {
+    IVec3::new(pos.x, pos.z, size.z - 1 - pos.y)
+}
+
+// we don't know what blocks look like here, so every block just gets a stable made up color
+fn block_color(name: &str) -> [u8; 4] {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    let [r, g, b, ..] = hasher.finish().to_le_bytes();
+    [r, g, b, 255]
+}
+
+fn write_chunk_header<W: Write>(w: &mut W, id: &[u8; 4], content: usize, children: usize) -> io::Result<()> {
+    w.write_all(id)?;
+    w.write_i32::<LittleEndian>(content as i32)?;
+    w.write_i32::<LittleEndian>(children as i32)
+}
+
+pub fn write_vox<W: Write>(mut w: W, universe: &Universe, clipboard: &Clipboard, mapping: &BlockMapping) -> io::Result<()> {
+    let size = clipboard.size;
+    if size.max_element() > MAX_SIZE {
+        return Err(invalid(format!("magicavoxel models can be at most {} blocks in each direction", MAX_SIZE)));
+    }
+
+    // blocks with a `vox:N` mapping get that palette index, everything else gets the first free one
+    let mut palette: HashMap<BlockId, u8> = HashMap::new();
+    let mut colors = [[0u8; 4]; 256];
+    let mut used = [false; 256];
+    let mut unmapped = vec![];
+    for block in clipboard.blocks.iter().copied() {
+        if block == AIR || palette.contains_key(&block) || unmapped.contains(&block) {
+            continue;
+        }
+        let name = universe.get_block_data_id(block).name.clone();
+        let index = mapping
+            .external_for(&name)
+            .and_then(|e| e.strip_prefix("vox:"))
+            .and_then(|i| i.parse::<u8>().ok())
+            .filter(|i| *i != 0 && !used[*i as usize]);
+        match index {
+            Some(i) => {
+                used[i as usize] = true;
+                colors[i as usize] = block_color(&name);
+                palette.insert(block, i);
+            }
+            None => unmapped.push(block),
+        }
+    }
+    for block in unmapped {
+        let i = (1..=255u8)
+            .find(|i| !used[*i as usize])
+            .ok_or_else(|| invalid("too many different blocks for a magicavoxel palette"))?;
+        used[i as usize] = true;
+        colors[i as usize] = block_color(&universe.get_block_data_id(block).name);
+        palette.insert(block, i);
+    }
+
+    let voxels: Vec<(IVec3, u8)> = clipboard
+        .region_at(IVec3::ZERO)
+        .iter()
+        .filter_map(|pos| palette.get(&clipboard.get(pos)).map(|i| (to_vox(pos, size), *i)))
+        .collect();
+
+    let size_len = 12;
+    let xyzi_len = 4 + 4 * voxels.len();
+    let rgba_len = 4 * 256;
+    let children = 3 * 12 + size_len + xyzi_len + rgba_len;
+
+    w.write_all(b"VOX ")?;
+    w.write_i32::<LittleEndian>(VOX_VERSION)?;
+    write_chunk_header(&mut w, b"MAIN", 0, children)?;
+
+    write_chunk_header(&mut w, b"SIZE", size_len, 0)?;
+    w.write_i32::<LittleEndian>(size.x)?;
+    w.write_i32::<LittleEndian>(size.z)?;
+    w.write_i32::<LittleEndian>(size.y)?;
+
+    write_chunk_header(&mut w, b"XYZI", xyzi_len, 0)?;
+    w.write_i32::<LittleEndian>(voxels.len() as i32)?;
+    for (pos, index) in voxels {
+        w.write_all(&[pos.x as u8, pos.y as u8, pos.z as u8, index])?;
+    }
+
+    // the palette in the file is shifted by one, entry 0 in the file is color index 1
+    write_chunk_header(&mut w, b"RGBA", rgba_len, 0)?;
+    for i in 0..256 {
+        w.write_all(&colors[(i + 1) % 256])?;
+    }
+    w.flush()
+}
+
+pub fn read_vox<R: Read>(mut r: R, universe: &Universe, mapping: &BlockMapping) -> io::Result<Clipboard> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != b"VOX " {
+        return Err(invalid("not a magicavoxel file"));
+    }
+    let _version = r.read_i32::<LittleEndian>()?;
+
+    let mut size = None;
+    let mut voxels = None;
+    loop {
+        // chunks are read flat, MAIN's children just come after its (empty) content
+        let mut id = [0; 4];
+        match r.read_exact(&mut id) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        }
+        let content_len = r.read_i32::<LittleEndian>()?;
+        let _children_len = r.read_i32::<LittleEndian>()?;
+        let content_len = u64::try_from(content_len).map_err(|_| invalid("negative chunk length"))?;
+
+        match &id {
+            b"SIZE" if size.is_none() => {
+                let s = IVec3::new(
+                    r.read_i32::<LittleEndian>()?,
+                    r.read_i32::<LittleEndian>()?,
+                    r.read_i32::<LittleEndian>()?,
+                );
+                if s.min_element() < 1 || s.max_element() > MAX_SIZE {
+                    return Err(invalid("model size out of range"));
+                }
+                io::copy(&mut (&mut r).take(content_len.saturating_sub(12)), &mut io::sink())?;
+                size = Some(s);
+            }
+            b"XYZI" if voxels.is_none() => {
+                let count = r.read_i32::<LittleEndian>()?;
+                let count = usize::try_from(count).map_err(|_| invalid("negative voxel count"))?;
+                let mut list = Vec::with_capacity(count.min(1 << 16));
+                for _ in 0..count {
+                    let mut v = [0; 4];
+                    r.read_exact(&mut v)?;
+                    list.push(v);
+                }
+                let read = 4 + 4 * count as u64;
+                io::copy(&mut (&mut r).take(content_len.saturating_sub(read)), &mut io::sink())?;
+                voxels = Some(list);
+            }
+            _ => {
+                // MAIN, palettes, materials, scene graph, extra models... none of it matters to us
+                io::copy(&mut (&mut r).take(content_len), &mut io::sink())?;
+            }
+        }
+    }
+
+    let vox_size = size.ok_or_else(|| invalid("file has no SIZE chunk"))?;
+    let voxels = voxels.ok_or_else(|| invalid("file has no XYZI chunk"))?;
+    let size = IVec3::new(vox_size.x, vox_size.z, vox_size.y);
+
+    let mut ids: HashMap<u8, BlockId> = HashMap::new();
+    let mut clipboard = Clipboard::new(size);
+    for [x, y, z, index] in voxels {
+        let vox_pos = IVec3::new(x as i32, y as i32, z as i32);
+        if vox_pos.cmpge(vox_size).any() {
+            return Err(invalid("voxel outside of model bounds"));
+        }
+        let block = *ids
+            .entry(index)
+            .or_insert_with(|| mapping.to_ours(universe, &format!("vox:{}", index)));
+        clipboard.set(from_vox(vox_pos, size), block);
+    }
+    Ok(clipboard)
+}
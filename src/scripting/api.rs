@@ -0,0 +1,252 @@
+// everything a script can call, registered onto the engine in one place so it's easy to see what mods can reach
+// there's no file, network or process access in here, and the engine itself has eval and imports turned off
+
+use std::sync::Arc;
+
+use bevy::math::f64::DVec3;
+use bevy::prelude::*;
+use parking_lot::Mutex;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+
+use crate::world::block::{BlockData, BlockProperty, BlockShape, BlockType, FluidFlow, ShapeBox, MAX_BLOCK_STATES};
+use crate::world::edits::EditCause;
+use crate::world::universe::Universe;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+// keeps a runaway script from freezing the game
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 64 * 1024;
+const MAX_ARRAY_SIZE: usize = 64 * 1024;
+const MAX_MAP_SIZE: usize = 64 * 1024;
+
+// things a script did that the game has to follow up on once the script is finished
+#[derive(Default)]
+pub struct ScriptEffects {
+    // model path and where to put it
+    pub spawns: Vec<(String, DVec3)>,
+}
+
+// what scripts see as `world`, the first argument of every handler and command
+#[derive(Clone)]
+pub struct ScriptWorld {
+    universe: Universe,
+    effects: Arc<Mutex<ScriptEffects>>,
+}
+
+impl ScriptWorld {
+    pub fn new(universe: Universe) -> Self {
+        Self { universe, effects: Arc::default() }
+    }
+
+    pub fn take_effects(&self) -> ScriptEffects {
+        std::mem::take(&mut self.effects.lock())
+    }
+
+    // the block's name, or () if the chunk isn't generated
+    fn get_block(&mut self, x: i64, y: i64, z: i64) -> Dynamic {
+        match self.universe.block_at_int(IVec3::new(x as i32, y as i32, z as i32)) {
+            Some(id) => self.universe.block_state_name(id).into(),
+            None => Dynamic::UNIT,
+        }
+    }
+
+    // false if the chunk isn't generated, an unknown block name is an error
+    fn set_block(&mut self, x: i64, y: i64, z: i64, name: &str) -> ScriptResult<bool> {
+        let block = self.universe.try_block_id_from_name(name)
+            .ok_or_else(|| format!("Unknown block \"{}\"", name))?;
+        let pos = IVec3::new(x as i32, y as i32, z as i32);
+        Ok(self.universe.set_block(pos, block, EditCause::Script).is_some())
+    }
+
+    fn spawn_entity(&mut self, model: &str, x: f64, y: f64, z: f64) {
+        self.effects.lock().spawns.push((model.to_string(), DVec3::new(x, y, z)));
+    }
+}
+
+// a console command a mod asked for, added to the console after the mod has loaded
+pub struct CommandRegistration {
+    pub name: String,
+    pub help: String,
+    // a trailing ? makes an argument optional
+    pub args: Vec<String>,
+    // the script function that runs it, called with the world and an array of the arguments
+    pub function: String,
+}
+
+// blocks and commands can only be registered while a mod's top level is running, this is None otherwise
+pub type Registrations = Arc<Mutex<Option<Vec<CommandRegistration>>>>;
+
+fn parse_block_type(name: &str) -> ScriptResult<BlockType> {
+    Ok(match name {
+        "empty" => BlockType::Empty,
+        "solid" => BlockType::OpaqueSolid,
+        "translucent" => BlockType::TranslucentSolid,
+        "fluid" => BlockType::Fluid,
+        "decoration" => BlockType::Decoration,
+        _ => return Err(format!("Unknown block type \"{}\", try solid, translucent, fluid, decoration or empty", name).into()),
+    })
+}
+
+// a box is [x1, y1, z1, x2, y2, z2] in fractions of a block
+fn parse_box(value: Dynamic) -> ScriptResult<ShapeBox> {
+    let numbers = value.try_cast::<Array>()
+        .and_then(|a| a.into_iter().map(|v| v.as_float().ok().or_else(|| v.as_int().ok().map(|i| i as f64))).collect::<Option<Vec<f64>>>())
+        .filter(|n| n.len() == 6)
+        .ok_or("Block boxes should be [x1, y1, z1, x2, y2, z2]")?;
+    let n: Vec<f32> = numbers.into_iter().map(|n| (n as f32).clamp(0.0, 1.0)).collect();
+    let (a, b) = (Vec3::new(n[0], n[1], n[2]), Vec3::new(n[3], n[4], n[5]));
+    Ok(ShapeBox { min: a.min(b), max: a.max(b) })
+}
+
+// flow: #{ ticks: 5, renews: true }, for fluids, both optional
+// a fluid spreads as far as its level property goes, so give it something like level: ["0", "1", "2", "3"]
+fn get_flow(map: &Map) -> ScriptResult<Option<FluidFlow>> {
+    let Some(flow) = map.get("flow") else {
+        return Ok(None);
+    };
+    let flow = flow.clone().try_cast::<Map>().ok_or("Block flow should be a map")?;
+    let default = FluidFlow::default();
+    let ticks = get_number(&flow, "ticks", default.ticks as f64)?.max(1.0) as u32;
+    let renews = match flow.get("renews") {
+        Some(r) => r.as_bool().map_err(|_| "Block flow renews should be true or false")?,
+        None => default.renews,
+    };
+    Ok(Some(FluidFlow { ticks, renews }))
+}
+
+// shape: "cube", "slab", "stairs" or "cross", or boxes: [[0, 0, 0, 1, 0.25, 1], ...] for anything else
+// slabs and stairs look at the half and facing properties, so give them those if they need to turn
+fn get_shape(map: &Map) -> ScriptResult<BlockShape> {
+    if let Some(boxes) = map.get("boxes") {
+        let boxes = boxes.clone().try_cast::<Array>().ok_or("Block boxes should be a list")?;
+        return Ok(BlockShape::Model(boxes.into_iter().map(parse_box).collect::<ScriptResult<_>>()?));
+    }
+    let Some(shape) = map.get("shape") else {
+        return Ok(BlockShape::Cube);
+    };
+    Ok(match shape.clone().into_string().map_err(|_| "Block shape should be a string")?.as_str() {
+        "cube" => BlockShape::Cube,
+        "slab" => BlockShape::Slab,
+        "stairs" => BlockShape::Stairs,
+        "cross" => BlockShape::Cross,
+        other => return Err(format!("Unknown block shape \"{}\", try cube, slab, stairs, cross or give it boxes", other).into()),
+    })
+}
+
+fn get_string(map: &Map, key: &str) -> ScriptResult<String> {
+    map.get(key)
+        .and_then(|v| v.clone().into_string().ok())
+        .ok_or_else(|| format!("Block needs a {} string", key).into())
+}
+
+fn get_number(map: &Map, key: &str, default: f64) -> ScriptResult<f64> {
+    match map.get(key) {
+        None => Ok(default),
+        Some(v) => v.as_float()
+            .or_else(|_| v.as_int().map(|i| i as f64))
+            .map_err(|_| format!("Block {} should be a number", key).into()),
+    }
+}
+
+// properties: #{ facing: ["north", "east", "south", "west"] }, the first value of each is the default
+fn get_properties(map: &Map) -> ScriptResult<Vec<BlockProperty>> {
+    let Some(properties) = map.get("properties") else {
+        return Ok(Vec::new());
+    };
+    let properties = properties.clone().try_cast::<Map>().ok_or("Block properties should be a map")?;
+    properties.into_iter()
+        .map(|(name, values)| {
+            let values = values.try_cast::<Array>()
+                .and_then(|a| a.into_iter().map(|v| v.into_string().ok()).collect::<Option<Vec<String>>>())
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| format!("Block property {} should be a list of values", name))?;
+            Ok(BlockProperty { name: name.to_string(), values })
+        })
+        .collect()
+}
+
+// register_block(#{ name: "glowstone", type: "solid", texture: "textures/block/glowstone.png", hardness: 0.3, drop: "glowstone" })
+fn register_block(universe: &Universe, registrations: &Registrations, map: Map) -> ScriptResult<()> {
+    if registrations.lock().is_none() {
+        return Err("Blocks can only be registered while the mod is loading".into());
+    }
+    let name = get_string(&map, "name")?;
+    if universe.try_block_id_from_name(&name).is_some() {
+        return Err(format!("There's already a block called \"{}\"", name).into());
+    }
+    let block_type = match map.get("type") {
+        Some(t) => parse_block_type(&t.clone().into_string().map_err(|_| "Block type should be a string")?)?,
+        None => BlockType::OpaqueSolid,
+    };
+    let drop = match map.get("drop") {
+        Some(d) if d.is_unit() => None,
+        Some(d) => Some(d.clone().into_string().map_err(|_| "Block drop should be a string or ()")?),
+        // drops itself, same as the builtin blocks
+        None => Some(name.clone()),
+    };
+    let block = BlockData {
+        texture_file: get_string(&map, "texture")?,
+        hardness: get_number(&map, "hardness", 1.0)? as f32,
+        properties: get_properties(&map)?,
+        shape: get_shape(&map)?,
+        flow: get_flow(&map)?,
+        name,
+        block_type,
+        drop,
+    };
+    if block.checked_state_count().is_none() {
+        return Err(format!(
+            "Block \"{}\" has too many combinations of property values, the most is {}",
+            block.name, MAX_BLOCK_STATES
+        ).into());
+    }
+    universe.register_block(block);
+    Ok(())
+}
+
+// register_command("pillar", "Builds a pillar under you", ["height", "block?"], "pillar")
+fn register_command(registrations: &Registrations, name: &str, help: &str, args: Array, function: &str) -> ScriptResult<()> {
+    let mut registrations = registrations.lock();
+    let Some(registrations) = registrations.as_mut() else {
+        return Err("Commands can only be registered while the mod is loading".into());
+    };
+    let args = args.into_iter()
+        .map(|a| a.into_string().map_err(|_| "Command arguments should be names"))
+        .collect::<Result<Vec<String>, _>>()?;
+    registrations.push(CommandRegistration {
+        name: name.to_string(),
+        help: help.to_string(),
+        args,
+        function: function.to_string(),
+    });
+    Ok(())
+}
+
+pub fn build_engine(universe: &Universe, registrations: &Registrations) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_ARRAY_SIZE)
+        .set_max_map_size(MAX_MAP_SIZE)
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .on_print(|text| info!("{}", text))
+        .on_debug(|text, source, pos| debug!("{} {}: {}", source.unwrap_or("mod"), pos, text));
+
+    engine.register_type_with_name::<ScriptWorld>("World")
+        .register_fn("get_block", ScriptWorld::get_block)
+        .register_fn("set_block", ScriptWorld::set_block)
+        .register_fn("spawn_entity", ScriptWorld::spawn_entity)
+        .register_get("seed", |w: &mut ScriptWorld| w.universe.seed as i64);
+
+    let (u, r) = (universe.clone(), registrations.clone());
+    engine.register_fn("register_block", move |map: Map| register_block(&u, &r, map));
+    let r = registrations.clone();
+    engine.register_fn("register_command", move |name: &str, help: &str, args: Array, function: &str| {
+        register_command(&r, name, help, args, function)
+    });
+    engine
+}
@@ -0,0 +1,263 @@
+// mods, written in rhai and loaded from every .rhai file in the mods directory (in name order)
+//
+// a mod's top level runs once at startup, where it can call
+//   register_block(#{ name, texture, type, hardness, drop, properties, shape })   type is solid/translucent/fluid/decoration/empty
+//     properties is a map of property name to its values, like #{ facing: ["north", "east", "south", "west"] }
+//     facing, axis and half are set when the player places the block
+//     shape is cube/slab/stairs/cross, or pass boxes: [[x1, y1, z1, x2, y2, z2], ...] instead
+//     fluids flow as far as their level property goes, flow: #{ ticks, renews } sets how fast and whether sources spread
+// blocks are named by their state wherever a mod sees one, like "log[axis=x]", and set_block takes the same
+//   register_command(name, help, ["arg", "optional_arg?"], "function_name")
+// a command's function gets called as function_name(world, args) and can return a string to print
+//
+// these functions get called if the mod defines them
+//   on_block_placed(world, x, y, z, block)
+//   on_block_broken(world, x, y, z, block)
+//   on_chunk_generated(world, cx, cy, cz)
+//   on_player_moved(world, x, y, z)       whenever the player steps into another block
+//
+// world has get_block(x, y, z), set_block(x, y, z, block), spawn_entity(model, x, y, z) and seed, see api.rs
+// on_block_placed and on_block_broken see every edit except the ones made by scripts, including edits from the server
+// mods only run on this side, a server doesn't know about their blocks and won't accept them
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use rhai::{CallFnOptions, Dynamic, Engine, FuncArgs, Scope, AST};
+
+use crate::chunk::chunk::BlockId;
+use crate::console::{Arg, ArgKind, CommandResult, ConsoleCommand, ConsoleCommands};
+use crate::player::ThisPlayer;
+use crate::position::universe_transform::UniverseTransform;
+use crate::world::edits::{BlockBrokenEvent, BlockPlacedEvent, EditCause};
+use crate::world::loading::ChunkGeneratedEvent;
+use crate::world::universe::Universe;
+
+pub mod api;
+use api::{build_engine, CommandRegistration, Registrations, ScriptEffects, ScriptWorld};
+
+const MODS_DIR: &str = "mods";
+
+// the game telling mods about something, see the list of handlers at the top
+#[derive(Event, Clone)]
+pub enum ScriptEvent {
+    BlockPlaced { pos: IVec3, block: BlockId },
+    BlockBroken { pos: IVec3, block: BlockId },
+    ChunkGenerated(IVec3),
+    PlayerMoved(IVec3),
+}
+
+struct Mod {
+    name: String,
+    ast: AST,
+}
+
+impl Mod {
+    fn defines(&self, function: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == function)
+    }
+}
+
+#[derive(Resource)]
+pub struct Mods {
+    engine: Engine,
+    mods: Vec<Mod>,
+}
+
+impl Mods {
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.mods.iter().map(|m| m.name.as_str())
+    }
+
+    // the top level isn't run again, it's only there for registering things
+    fn call(&self, m: &Mod, function: &str, args: impl FuncArgs) -> Result<Dynamic, String> {
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine.call_fn_with_options(options, &mut Scope::new(), &m.ast, function, args)
+            .map_err(|e| format!("{}: {}", m.name, e))
+    }
+}
+
+fn mod_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "rhai"))
+        .collect();
+    // so blocks from mods always get the same ids
+    files.sort();
+    files
+}
+
+fn load_mod(engine: &Engine, registrations: &Registrations, path: &Path) -> Result<(Mod, Vec<CommandRegistration>), String> {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut ast = engine.compile(source).map_err(|e| e.to_string())?;
+    ast.set_source(name.as_str());
+
+    *registrations.lock() = Some(Vec::new());
+    let result = engine.run_ast(&ast);
+    let commands = registrations.lock().take().unwrap_or_default();
+    result.map_err(|e| e.to_string())?;
+    Ok((Mod { name, ast }, commands))
+}
+
+// runs after the default blocks are registered, so mod blocks always come after them
+pub fn load_mods(world: &mut World) {
+    let universe = world.resource::<Universe>().clone();
+    let registrations = Registrations::default();
+    let engine = build_engine(&universe, &registrations);
+
+    let mut mods = Vec::new();
+    for path in mod_files(Path::new(MODS_DIR)) {
+        match load_mod(&engine, &registrations, &path) {
+            Ok((m, commands)) => {
+                info!("Loaded mod {}", m.name);
+                for command in commands {
+                    add_console_command(world, mods.len(), command);
+                }
+                mods.push(m);
+            }
+            Err(e) => error!("Couldn't load mod {}: {}", path.display(), e),
+        }
+    }
+    world.insert_resource(Mods { engine, mods });
+    world.resource_mut::<ConsoleCommands>().register(ConsoleCommand {
+        name: "mods",
+        help: "Lists the mods that loaded",
+        args: &[],
+        run: Arc::new(list_mods),
+    });
+}
+
+fn list_mods(world: &mut World, _args: &[&str]) -> CommandResult {
+    let names: Vec<&str> = world.resource::<Mods>().names().collect();
+    if names.is_empty() {
+        return Ok(format!("No mods loaded, put .rhai files in {}/", MODS_DIR));
+    }
+    Ok(names.join("\n"))
+}
+
+fn add_console_command(world: &mut World, index: usize, command: CommandRegistration) {
+    // mods are only loaded once, so leaking these for the console's 'static strings is fine
+    let args: Vec<Arg> = command.args.iter()
+        .map(|a| match a.strip_suffix('?') {
+            Some(name) => Arg::optional(name.to_string().leak(), ArgKind::Text),
+            None => Arg::required(a.clone().leak(), ArgKind::Text),
+        })
+        .collect();
+    let function = command.function;
+    world.resource_mut::<ConsoleCommands>().register(ConsoleCommand {
+        name: command.name.leak(),
+        help: command.help.leak(),
+        args: args.leak(),
+        run: Arc::new(move |world, args| run_mod_command(world, index, &function, args)),
+    });
+}
+
+fn run_mod_command(world: &mut World, index: usize, function: &str, args: &[&str]) -> CommandResult {
+    let script_world = ScriptWorld::new(world.resource::<Universe>().clone());
+    let args: rhai::Array = args.iter().map(|a| Dynamic::from(a.to_string())).collect();
+    let result = {
+        let mods = world.resource::<Mods>();
+        mods.call(&mods.mods[index], function, (script_world.clone(), args))
+    };
+    apply_effects(world, script_world.take_effects());
+
+    let value = result?;
+    if value.is_unit() {
+        return Ok(String::new());
+    }
+    Ok(value.to_string())
+}
+
+// spawns what scripts asked for, their edits get picked up like anyone else's
+fn apply_effects(world: &mut World, effects: ScriptEffects) {
+    for (model, position) in effects.spawns {
+        let scene = world.resource::<AssetServer>().load(GltfAssetLabel::Scene(0).from_asset(model));
+        world.spawn((
+            SceneBundle { scene, ..default() },
+            UniverseTransform::from_dim_xyz(0, (position.x, position.y, position.z)),
+        ));
+    }
+}
+
+fn handler_call(event: &ScriptEvent, universe: &Universe) -> (&'static str, Vec<Dynamic>) {
+    let block_name = |id: &BlockId| Dynamic::from(universe.block_state_name(*id));
+    let xyz = |p: &IVec3| [p.x, p.y, p.z].map(|n| Dynamic::from(n as i64));
+    match event {
+        ScriptEvent::BlockPlaced { pos, block } => ("on_block_placed", [xyz(pos).to_vec(), vec![block_name(block)]].concat()),
+        ScriptEvent::BlockBroken { pos, block } => ("on_block_broken", [xyz(pos).to_vec(), vec![block_name(block)]].concat()),
+        ScriptEvent::ChunkGenerated(coords) => ("on_chunk_generated", xyz(coords).to_vec()),
+        ScriptEvent::PlayerMoved(pos) => ("on_player_moved", xyz(pos).to_vec()),
+    }
+}
+
+fn run_event_handlers(world: &mut World) {
+    let events: Vec<ScriptEvent> = world.resource_mut::<Events<ScriptEvent>>().drain().collect();
+    if events.is_empty() {
+        return;
+    }
+    let script_world = ScriptWorld::new(world.resource::<Universe>().clone());
+    {
+        let mods = world.resource::<Mods>();
+        let universe = world.resource::<Universe>();
+        for event in &events {
+            let (function, args) = handler_call(event, universe);
+            for m in mods.mods.iter().filter(|m| m.defines(function)) {
+                let mut args = args.clone();
+                args.insert(0, Dynamic::from(script_world.clone()));
+                if let Err(e) = mods.call(m, function, args) {
+                    warn!("{}", e);
+                }
+            }
+        }
+    }
+    apply_effects(world, script_world.take_effects());
+}
+
+// edits made by scripts are left out, otherwise a handler that places a block could keep setting itself off
+fn forward_block_events(
+    mut ev_placed: EventReader<BlockPlacedEvent>,
+    mut ev_broken: EventReader<BlockBrokenEvent>,
+    mut ev_script: EventWriter<ScriptEvent>,
+) {
+    for BlockBrokenEvent(edit) in ev_broken.read().filter(|e| e.0.cause != EditCause::Script) {
+        ev_script.send(ScriptEvent::BlockBroken { pos: edit.pos, block: edit.old });
+    }
+    for BlockPlacedEvent(edit) in ev_placed.read().filter(|e| e.0.cause != EditCause::Script) {
+        ev_script.send(ScriptEvent::BlockPlaced { pos: edit.pos, block: edit.new });
+    }
+}
+
+fn forward_generated_chunks(mut ev_generated: EventReader<ChunkGeneratedEvent>, mut ev_script: EventWriter<ScriptEvent>) {
+    for ChunkGeneratedEvent(coords) in ev_generated.read() {
+        ev_script.send(ScriptEvent::ChunkGenerated(*coords));
+    }
+}
+
+fn watch_player_movement(
+    player: Query<&UniverseTransform, With<ThisPlayer>>,
+    mut last: Local<Option<IVec3>>,
+    mut ev_script: EventWriter<ScriptEvent>,
+) {
+    let pos = player.single().loc.position.floor().as_ivec3();
+    if *last != Some(pos) {
+        *last = Some(pos);
+        ev_script.send(ScriptEvent::PlayerMoved(pos));
+    }
+}
+
+// load_mods has to be run by whoever adds this, once the default blocks are in
+pub struct ScriptingPlugin;
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ScriptEvent>()
+            .add_systems(Update, (
+                (forward_block_events, forward_generated_chunks, watch_player_movement),
+                run_event_handlers,
+            ).chain());
+    }
+}
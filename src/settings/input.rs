@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::EguiContexts;
+use serde::{Deserialize, Serialize};
+
+use super::Settings;
+
+// everything the player can do with a button
+// these names are what shows up in the config file, so renaming one resets it to the default binding
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Descend,
+    Sprint,
+    ToggleFlight,
+
+    Break,
+    Place,
+    PickBlock,
+    Inventory,
+    HotbarSlot1,
+    HotbarSlot2,
+    HotbarSlot3,
+    HotbarSlot4,
+    HotbarSlot5,
+    HotbarSlot6,
+    HotbarSlot7,
+    HotbarSlot8,
+    HotbarSlot9,
+    HotbarNext,
+    HotbarPrevious,
+
+    CaptureCursor,
+    // opens the pause menu, and lets go of the cursor
+    Pause,
+    CycleGameMode,
+    // first person, or behind the player
+    ToggleCameraView,
+    ToggleControls,
+    ToggleConsole,
+    ToggleWorldEdit,
+    SetEditCorner1,
+    SetEditCorner2,
+
+    ToggleDebugInfo,
+    // held down for the other debug actions
+    DebugModifier,
+    DebugLookAhead,
+    DebugResetYaw,
+    DebugRotateLeft,
+    DebugRotateRight,
+    DebugRoundPosition,
+}
+
+impl Action {
+    pub const ALL: [Action; 39] = [
+        Action::MoveForward, Action::MoveBackward, Action::MoveLeft, Action::MoveRight,
+        Action::Jump, Action::Descend, Action::Sprint, Action::ToggleFlight,
+        Action::Break, Action::Place, Action::PickBlock, Action::Inventory,
+        Action::HotbarSlot1, Action::HotbarSlot2, Action::HotbarSlot3,
+        Action::HotbarSlot4, Action::HotbarSlot5, Action::HotbarSlot6,
+        Action::HotbarSlot7, Action::HotbarSlot8, Action::HotbarSlot9,
+        Action::HotbarNext, Action::HotbarPrevious,
+        Action::CaptureCursor, Action::Pause, Action::CycleGameMode, Action::ToggleCameraView, Action::ToggleControls,
+        Action::ToggleConsole, Action::ToggleWorldEdit, Action::SetEditCorner1, Action::SetEditCorner2,
+        Action::ToggleDebugInfo, Action::DebugModifier, Action::DebugLookAhead, Action::DebugResetYaw,
+        Action::DebugRotateLeft, Action::DebugRotateRight, Action::DebugRoundPosition,
+    ];
+
+    pub const HOTBAR: [Action; 9] = [
+        Action::HotbarSlot1, Action::HotbarSlot2, Action::HotbarSlot3,
+        Action::HotbarSlot4, Action::HotbarSlot5, Action::HotbarSlot6,
+        Action::HotbarSlot7, Action::HotbarSlot8, Action::HotbarSlot9,
+    ];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    // on any connected gamepad
+    Gamepad(GamepadButtonType),
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(k) => write!(f, "{:?}", k),
+            Binding::Mouse(m) => write!(f, "Mouse {:?}", m),
+            Binding::Gamepad(b) => write!(f, "Pad {:?}", b),
+        }
+    }
+}
+
+// which buttons do which actions, an action can have any number of buttons
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InputMap(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use Binding::*;
+        use GamepadButtonType as Pad;
+        let mut map = BTreeMap::new();
+        let mut bind = |action, bindings: &[Binding]| {
+            map.insert(action, bindings.to_vec());
+        };
+
+        // moving and looking around on a gamepad use the sticks, see ActionInput::movement_stick
+        bind(Action::MoveForward, &[Key(KeyCode::KeyW)]);
+        bind(Action::MoveBackward, &[Key(KeyCode::KeyS)]);
+        bind(Action::MoveLeft, &[Key(KeyCode::KeyA)]);
+        bind(Action::MoveRight, &[Key(KeyCode::KeyD)]);
+        bind(Action::Jump, &[Key(KeyCode::Space), Gamepad(Pad::South)]);
+        bind(Action::Descend, &[Key(KeyCode::ShiftLeft), Gamepad(Pad::East)]);
+        bind(Action::Sprint, &[Key(KeyCode::ControlLeft), Gamepad(Pad::LeftThumb)]);
+        bind(Action::ToggleFlight, &[Key(KeyCode::KeyV), Gamepad(Pad::DPadUp)]);
+
+        bind(Action::Break, &[Mouse(MouseButton::Left), Gamepad(Pad::RightTrigger2)]);
+        bind(Action::Place, &[Mouse(MouseButton::Right), Gamepad(Pad::LeftTrigger2)]);
+        bind(Action::PickBlock, &[Mouse(MouseButton::Middle), Gamepad(Pad::West)]);
+        bind(Action::Inventory, &[Key(KeyCode::KeyE), Gamepad(Pad::North)]);
+        let digits = [
+            KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3,
+            KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6,
+            KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+        ];
+        for (action, key) in Action::HOTBAR.into_iter().zip(digits) {
+            bind(action, &[Key(key)]);
+        }
+        // the bumpers, not the analog triggers
+        bind(Action::HotbarNext, &[Gamepad(Pad::RightTrigger)]);
+        bind(Action::HotbarPrevious, &[Gamepad(Pad::LeftTrigger)]);
+
+        bind(Action::CaptureCursor, &[Mouse(MouseButton::Left)]);
+        bind(Action::Pause, &[Key(KeyCode::Escape), Gamepad(Pad::Start)]);
+        bind(Action::CycleGameMode, &[Key(KeyCode::F2)]);
+        bind(Action::ToggleCameraView, &[Key(KeyCode::F5), Gamepad(Pad::DPadDown)]);
+        bind(Action::ToggleControls, &[Key(KeyCode::F1)]);
+        bind(Action::ToggleConsole, &[Key(KeyCode::Backquote)]);
+        bind(Action::ToggleWorldEdit, &[Key(KeyCode::F4)]);
+        bind(Action::SetEditCorner1, &[Key(KeyCode::BracketLeft)]);
+        bind(Action::SetEditCorner2, &[Key(KeyCode::BracketRight)]);
+
+        bind(Action::ToggleDebugInfo, &[Key(KeyCode::F3), Gamepad(Pad::Select)]);
+        bind(Action::DebugModifier, &[Key(KeyCode::ControlLeft), Key(KeyCode::ControlRight)]);
+        bind(Action::DebugLookAhead, &[Key(KeyCode::KeyQ)]);
+        bind(Action::DebugResetYaw, &[Key(KeyCode::KeyX)]);
+        bind(Action::DebugRotateLeft, &[Key(KeyCode::KeyZ)]);
+        bind(Action::DebugRotateRight, &[Key(KeyCode::KeyC)]);
+        bind(Action::DebugRoundPosition, &[Key(KeyCode::KeyF)]);
+
+        InputMap(map)
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    // replaces whatever the action was bound to
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.0.insert(action, vec![binding]);
+    }
+
+    pub fn add_binding(&mut self, action: Action, binding: Binding) {
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    // kept as an empty list rather than removed, otherwise loading would put the default back
+    pub fn unbind(&mut self, action: Action) {
+        self.0.insert(action, Vec::new());
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        match InputMap::default().0.remove(&action) {
+            Some(bindings) => self.0.insert(action, bindings),
+            None => self.0.remove(&action),
+        };
+    }
+
+    // config files only need to list what they change, everything else keeps its default
+    pub fn fill_defaults(&mut self) {
+        for (action, bindings) in InputMap::default().0 {
+            self.0.entry(action).or_insert(bindings);
+        }
+    }
+}
+
+// scales a stick so everything inside the deadzone is zero, and it still reaches full tilt at the edge
+fn apply_deadzone(stick: Vec2, deadzone: f32) -> Vec2 {
+    let length = stick.length();
+    if length <= deadzone || deadzone >= 1.0 {
+        return Vec2::ZERO;
+    }
+    let scaled = ((length - deadzone) / (1.0 - deadzone)).min(1.0);
+    stick * (scaled / length)
+}
+
+// whether egui is using the keyboard (someone is typing in a text box)
+// checked in PreUpdate so it's the same for every system in a frame
+#[derive(Resource, Default)]
+pub struct UiFocus {
+    pub keyboard: bool,
+}
+
+pub fn update_ui_focus(mut egui: EguiContexts, mut focus: ResMut<UiFocus>) {
+    focus.keyboard = egui.ctx_mut().wants_keyboard_input();
+}
+
+// reads actions instead of raw buttons, so systems don't care what they're bound to
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    pub settings: Res<'w, Settings>,
+    focus: Res<'w, UiFocus>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+impl ActionInput<'_> {
+    fn any<K, M, G>(&self, action: Action, key: K, mouse: M, gamepad: G) -> bool
+    where
+        K: Fn(&ButtonInput<KeyCode>, KeyCode) -> bool,
+        M: Fn(&ButtonInput<MouseButton>, MouseButton) -> bool,
+        G: Fn(&ButtonInput<GamepadButton>, GamepadButton) -> bool,
+    {
+        self.settings.bindings.bindings(action).iter().any(|b| match *b {
+            // typing into a text box shouldn't also walk around
+            Binding::Key(k) => !self.focus.keyboard && key(&self.keys, k),
+            Binding::Mouse(m) => mouse(&self.mouse, m),
+            Binding::Gamepad(button) => self.gamepads.iter()
+                .any(|pad| gamepad(&self.gamepad_buttons, GamepadButton::new(pad, button))),
+        })
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.any(action, |i, k| i.pressed(k), |i, m| i.pressed(m), |i, g| i.pressed(g))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.any(action, |i, k| i.just_pressed(k), |i, m| i.just_pressed(m), |i, g| i.just_pressed(g))
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.any(action, |i, k| i.just_released(k), |i, m| i.just_released(m), |i, g| i.just_released(g))
+    }
+
+    // the most tilted stick out of every gamepad, with the deadzone taken out
+    fn stick(&self, x: GamepadAxisType, y: GamepadAxisType, deadzone: f32) -> Vec2 {
+        self.gamepads.iter()
+            .map(|pad| Vec2::new(
+                self.gamepad_axes.get(GamepadAxis::new(pad, x)).unwrap_or(0.0),
+                self.gamepad_axes.get(GamepadAxis::new(pad, y)).unwrap_or(0.0),
+            ))
+            .map(|stick| apply_deadzone(stick, deadzone))
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    // x is right and y is forward, each from -1 to 1
+    pub fn movement_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY, self.settings.gamepad_move_deadzone)
+    }
+
+    // x is right and y is up, each from -1 to 1
+    pub fn look_stick(&self) -> Vec2 {
+        self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY, self.settings.gamepad_look_deadzone)
+    }
+}
+
+// the action waiting for its next button press, if any
+#[derive(Resource, Default)]
+pub struct Rebinding(pub Option<Action>);
+
+pub fn capture_rebinding(
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<Settings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+) {
+    let Some(action) = rebinding.0 else {
+        return;
+    };
+
+    let pressed = keys.get_just_pressed().next().map(|k| Binding::Key(*k))
+        .or_else(|| mouse.get_just_pressed().next().map(|m| Binding::Mouse(*m)))
+        .or_else(|| gamepad_buttons.get_just_pressed().next().map(|g| Binding::Gamepad(g.button_type)));
+    let Some(binding) = pressed else {
+        return;
+    };
+    if binding == Binding::Key(KeyCode::Escape) {
+        rebinding.0 = None;
+        return;
+    }
+
+    settings.bindings.rebind(action, binding);
+    rebinding.0 = None;
+    info!("Bound {:?} to {}", action, binding);
+    settings.save_or_warn();
+}
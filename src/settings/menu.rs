@@ -0,0 +1,125 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::input::{capture_rebinding, Action, ActionInput, Rebinding};
+use super::{ControlsUiState, Settings};
+
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    pub open: bool,
+    // settings were changed since the menu opened, and need writing to disk when it closes
+    dirty: bool,
+}
+
+// the game only simulates while the menu is closed
+fn open_menu(menu: &mut PauseMenu, time: &mut Time<Virtual>) {
+    menu.open = true;
+    time.pause();
+}
+
+fn close_menu(menu: &mut PauseMenu, time: &mut Time<Virtual>, settings: &Settings) {
+    menu.open = false;
+    time.unpause();
+    if menu.dirty {
+        settings.save_or_warn();
+        menu.dirty = false;
+    }
+}
+
+fn toggle_pause_menu(
+    input: ActionInput,
+    rebinding: Res<Rebinding>,
+    mut menu: ResMut<PauseMenu>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    // escape is for cancelling the rebind, not for this
+    if rebinding.0.is_some() || !input.just_pressed(Action::Pause) {
+        return;
+    }
+    if menu.open {
+        close_menu(&mut menu, &mut time, &input.settings);
+    } else {
+        open_menu(&mut menu, &mut time);
+    }
+}
+
+// run condition for gameplay input that shouldn't happen behind the menu
+pub fn not_paused(menu: Res<PauseMenu>) -> bool {
+    !menu.open
+}
+
+fn slider<T: egui::emath::Numeric>(ui: &mut egui::Ui, value: &mut T, range: std::ops::RangeInclusive<T>, text: &str) -> bool {
+    ui.add(egui::Slider::new(value, range).text(text)).changed()
+}
+
+fn pause_menu(
+    mut egui: EguiContexts,
+    mut menu: ResMut<PauseMenu>,
+    mut settings: ResMut<Settings>,
+    mut controls: ResMut<ControlsUiState>,
+    mut time: ResMut<Time<Virtual>>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    let mut resume = false;
+    let mut changed = false;
+    // Settings is only marked as changed when something actually moves,
+    // so systems watching for changes (like the tick rate) don't fire every frame the menu is open
+    let s = settings.bypass_change_detection();
+
+    egui::Window::new("Paused")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(egui.ctx_mut(), |ui| {
+            ui.heading("Video");
+            changed |= slider(ui, &mut s.horizontal_render_distance, 1..=32, "Horizontal render distance");
+            changed |= slider(ui, &mut s.vertical_render_distance, 1..=16, "Vertical render distance");
+
+            ui.heading("Controls");
+            changed |= slider(ui, &mut s.mouse_sensitivity, 0.0005..=0.02, "Mouse sensitivity");
+            changed |= slider(ui, &mut s.gamepad_look_sensitivity, 0.5..=10.0, "Gamepad look sensitivity");
+            changed |= slider(ui, &mut s.gamepad_move_deadzone, 0.0..=0.9, "Movement stick deadzone");
+            changed |= slider(ui, &mut s.gamepad_look_deadzone, 0.0..=0.9, "Look stick deadzone");
+            if ui.button("Key bindings...").clicked() {
+                controls.show_window = true;
+            }
+
+            ui.heading("Simulation");
+            changed |= slider(ui, &mut s.tick_rate, 10.0..=240.0, "Ticks per second");
+
+            ui.separator();
+            if ui.button("Reset to defaults").clicked() {
+                // bindings have their own reset in the controls window
+                let bindings = std::mem::take(&mut s.bindings);
+                *s = Settings { bindings, ..default() };
+                changed = true;
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Resume").clicked() {
+                    resume = true;
+                }
+                if ui.button("Quit").clicked() {
+                    ev_exit.send(AppExit::Success);
+                }
+            });
+        });
+
+    if changed {
+        // everything reading Settings picks this up right away, including chunk loading
+        settings.set_changed();
+        menu.dirty = true;
+    }
+    if resume {
+        close_menu(&mut menu, &mut time, &settings);
+    }
+}
+
+pub struct PauseMenuPlugin;
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>()
+            .add_systems(Update, toggle_pause_menu.before(capture_rebinding))
+            .add_systems(Update, pause_menu.after(toggle_pause_menu).run_if(|m: Res<PauseMenu>| {m.open}));
+    }
+}
@@ -1,19 +1,185 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+pub mod input;
+pub mod menu;
+use input::{capture_rebinding, update_ui_focus, Action, ActionInput, InputMap, Rebinding, UiFocus};
+use menu::PauseMenuPlugin;
 
-#[derive(Resource)]
+// anything missing from the config file keeps its default, so old config files keep working
+#[derive(Resource, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     // rendering
     pub horizontal_render_distance: u8, // do i expect anyone to break the bounds of a u8? no. should i give the foolish the option? maybe later.
     pub vertical_render_distance: u8,
 
     // controls
-    pub mouse_sensitivity: f32
+    pub mouse_sensitivity: f32,
+    pub gamepad_look_sensitivity: f32, // radians per second with the stick all the way over
+    pub gamepad_move_deadzone: f32, // how far the sticks have to move before they do anything, from 0 to 1
+    pub gamepad_look_deadzone: f32,
+
+    // simulation
+    pub tick_rate: f64, // gameplay ticks per second, rendering is interpolated between them
+
+    // which buttons do what, last because it's a table in the config file
+    pub bindings: InputMap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            horizontal_render_distance: 8,
+            vertical_render_distance: 8,
+
+            mouse_sensitivity: 0.005,
+            gamepad_look_sensitivity: 3.0,
+            gamepad_move_deadzone: 0.15,
+            gamepad_look_deadzone: 0.15,
+
+            tick_rate: 60.0,
+
+            bindings: InputMap::default()
+        }
+    }
+}
+
+impl Settings {
+    // usually ~/.config/dirlaku/settings.toml
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("dirlaku").join("settings.toml"))
+    }
+
+    // the defaults are used if the file is missing or broken, a broken file is left alone so it can be fixed by hand
+    pub fn load() -> Settings {
+        let Some(path) = Self::path() else {
+            warn!("No config directory, using default settings");
+            return Settings::default();
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let settings = Settings::default();
+                settings.save_or_warn();
+                return settings;
+            }
+            Err(e) => {
+                warn!("Couldn't read {}: {}, using default settings", path.display(), e);
+                return Settings::default();
+            }
+        };
+
+        match toml::from_str::<Settings>(&text) {
+            Ok(mut settings) => {
+                settings.bindings.fill_defaults();
+                info!("Loaded settings from {}", path.display());
+                settings
+            }
+            Err(e) => {
+                warn!("Couldn't parse {}: {}, using default settings", path.display(), e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    pub fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            warn!("Couldn't save settings: {}", e);
+        }
+    }
 }
 
-pub const DEFAULT_SETTINGS : Settings = Settings {
-    horizontal_render_distance: 8,
-    vertical_render_distance: 8,
+// keeps the fixed timestep in sync with the tick rate setting
+pub fn apply_tick_rate(settings: Res<Settings>, mut fixed_time: ResMut<Time<Fixed>>) {
+    if settings.is_changed() {
+        fixed_time.set_timestep_hz(settings.tick_rate);
+    }
+}
 
-    mouse_sensitivity: 0.005
-};
+#[derive(Resource, Default)]
+pub struct ControlsUiState {
+    pub show_window: bool,
+}
+
+fn toggle_controls(input: ActionInput, mut ui_state: ResMut<ControlsUiState>) {
+    if input.just_pressed(Action::ToggleControls) {
+        ui_state.show_window = !ui_state.show_window;
+    }
+}
 
+fn controls_window(
+    mut egui: EguiContexts,
+    mut ui_state: ResMut<ControlsUiState>,
+    mut settings: ResMut<Settings>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let mut open = ui_state.show_window;
+    let mut changed = false;
+
+    egui::Window::new("Controls").open(&mut open).vscroll(true).show(egui.ctx_mut(), |ui| {
+        ui.label("Click an action, then press the button to bind it to. Escape cancels.");
+        egui::Grid::new("bindings").striped(true).show(ui, |ui| {
+            for action in Action::ALL {
+                ui.label(format!("{:?}", action));
+
+                let text = if rebinding.0 == Some(action) {
+                    String::from("press a button...")
+                } else {
+                    let bound: Vec<String> = settings.bindings.bindings(action).iter().map(|b| b.to_string()).collect();
+                    if bound.is_empty() { String::from("unbound") } else { bound.join(", ") }
+                };
+                if ui.button(text).clicked() {
+                    rebinding.0 = Some(action);
+                }
+                if ui.button("Clear").clicked() {
+                    settings.bindings.unbind(action);
+                    changed = true;
+                }
+                if ui.button("Reset").clicked() {
+                    settings.bindings.reset(action);
+                    changed = true;
+                }
+                ui.end_row();
+            }
+        });
+    });
+
+    if changed {
+        settings.save_or_warn();
+    }
+    if !open {
+        rebinding.0 = None;
+    }
+    ui_state.show_window = open;
+}
+
+pub struct SettingsPlugin;
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(PauseMenuPlugin)
+            .insert_resource(Settings::load())
+            .init_resource::<Rebinding>()
+            .init_resource::<UiFocus>()
+            .add_systems(PreUpdate, update_ui_focus)
+            .init_resource::<ControlsUiState>()
+            .add_systems(First, apply_tick_rate)
+            .add_systems(Update, (capture_rebinding, toggle_controls))
+            .add_systems(Update, controls_window.run_if(|s: Res<ControlsUiState>| {s.show_window}));
+    }
+}
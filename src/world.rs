@@ -1,4 +1,10 @@
+#[cfg(feature = "client")]
 pub mod loading;
 pub mod universe;
 pub mod block;
-pub mod block_materials;
\ No newline at end of file
+#[cfg(feature = "client")]
+pub mod block_materials;
+pub mod region;
+pub mod edits;
+pub mod block_entity;
+pub mod fluid;
@@ -1,4 +1,9 @@
+use bevy::prelude::*;
 use block_mesh::VoxelVisibility;
+use super::universe::Universe;
+
+// every state gets its own id, so a block with too many property combinations would eat the id space
+pub const MAX_BLOCK_STATES: u32 = 4096;
 
 
 pub enum BlockType {
@@ -6,6 +11,8 @@ pub enum BlockType {
     OpaqueSolid,
     TranslucentSolid,
     Fluid,
+    // drawn but nothing collides with it, like plants
+    Decoration,
 }
 
 impl BlockType {
@@ -14,13 +21,299 @@ impl BlockType {
             Self::Empty => VoxelVisibility::Empty,
             Self::OpaqueSolid => VoxelVisibility::Opaque,
             Self::TranslucentSolid => VoxelVisibility::Translucent,
-            Self::Fluid => VoxelVisibility::Translucent
+            Self::Fluid => VoxelVisibility::Translucent,
+            Self::Decoration => VoxelVisibility::Translucent
+        }
+    }
+
+    // whether entities collide with this block
+    pub fn is_solid(&self) -> bool {
+        match self {
+            Self::OpaqueSolid | Self::TranslucentSolid => true,
+            Self::Empty | Self::Fluid | Self::Decoration => false
+        }
+    }
+}
+
+// something that can differ between placed copies of a block, like which way it faces
+// every combination of values is its own state id, so keep the value lists short
+#[derive(Clone, PartialEq)]
+pub struct BlockProperty {
+    pub name: String,
+    // the first one is the default
+    pub values: Vec<String>,
+}
+
+impl BlockProperty {
+    pub fn new(name: &str, values: &[&str]) -> Self {
+        BlockProperty {
+            name: String::from(name),
+            values: values.iter().map(|v| String::from(*v)).collect(),
+        }
+    }
+
+    // set from the way the player is looking when it's placed
+    pub fn facing() -> Self {
+        Self::new("facing", &["north", "east", "south", "west"])
+    }
+
+    // set from the face it was placed against
+    pub fn axis() -> Self {
+        Self::new("axis", &["y", "x", "z"])
+    }
+
+    // set from where on the face it was placed
+    pub fn half() -> Self {
+        Self::new("half", &["bottom", "top"])
+    }
+
+    pub fn waterlogged() -> Self {
+        Self::new("waterlogged", &["false", "true"])
+    }
+
+    // 0 to max
+    pub fn level(max: u32) -> Self {
+        BlockProperty {
+            name: String::from("level"),
+            values: (0..=max).map(|l| l.to_string()).collect(),
+        }
+    }
+
+    pub fn index_of(&self, value: &str) -> Option<u32> {
+        self.values.iter().position(|v| v == value).map(|i| i as u32)
+    }
+}
+
+// a box inside a block, in fractions of a block from its minimum corner
+#[derive(Clone, Copy, PartialEq)]
+pub struct ShapeBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl ShapeBox {
+    pub const FULL: ShapeBox = ShapeBox::new([0.0; 3], [1.0; 3]);
+
+    pub const fn new(min: [f32; 3], max: [f32; 3]) -> Self {
+        ShapeBox { min: Vec3::from_array(min), max: Vec3::from_array(max) }
+    }
+
+    // upside down, for the top half of slabs and stairs
+    fn flipped(self) -> Self {
+        ShapeBox {
+            min: Vec3::new(self.min.x, 1.0 - self.max.y, self.min.z),
+            max: Vec3::new(self.max.x, 1.0 - self.min.y, self.max.z),
         }
     }
 }
 
+// which way north/east/south/west are, see UniverseTransform::get_compass_quadrant
+pub fn facing_direction(facing: &str) -> IVec3 {
+    match facing {
+        "north" => IVec3::X,
+        "east" => IVec3::Z,
+        "south" => IVec3::NEG_X,
+        "west" => IVec3::NEG_Z,
+        _ => IVec3::ZERO,
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum BlockShape {
+    // the only shape greedy meshing handles, everything else gets meshed box by box
+    Cube,
+    // the bottom half of a block, or the top half if the half property is top
+    Slab,
+    // goes up towards where the facing property points, upside down if half is top
+    Stairs,
+    // two planes crossed diagonally, for plants
+    Cross,
+    // any boxes at all
+    Model(Vec<ShapeBox>),
+}
+
+impl BlockShape {
+    pub fn is_cube(&self) -> bool {
+        *self == BlockShape::Cube
+    }
+
+    // what the block takes up, for collision, raycasting and meshing everything except crosses
+    // properties are the state's, as from Universe::block_properties
+    pub fn boxes(&self, properties: &[(String, String)]) -> Vec<ShapeBox> {
+        let property = |name: &str| properties.iter().find(|(p, _)| p == name).map(|(_, v)| v.as_str());
+        let top = property("half") == Some("top");
+        let flip = |b: ShapeBox| if top { b.flipped() } else { b };
+
+        match self {
+            BlockShape::Cube => vec![ShapeBox::FULL],
+            BlockShape::Slab => vec![flip(ShapeBox::new([0.0; 3], [1.0, 0.5, 1.0]))],
+            BlockShape::Stairs => {
+                // the upper step covers the half of the block facing points into
+                let d = facing_direction(property("facing").unwrap_or("north"));
+                let mut step = ShapeBox::new([0.0, 0.5, 0.0], [1.0; 3]);
+                for axis in [0, 2] {
+                    match d[axis] {
+                        1 => step.min[axis] = 0.5,
+                        -1 => step.max[axis] = 0.5,
+                        _ => {}
+                    }
+                }
+                vec![flip(ShapeBox::new([0.0; 3], [1.0, 0.5, 1.0])), flip(step)]
+            }
+            BlockShape::Cross => vec![ShapeBox::new([0.15, 0.0, 0.15], [0.85, 1.0, 0.85])],
+            BlockShape::Model(boxes) => boxes.clone(),
+        }
+    }
+}
+
+// how a BlockType::Fluid block flows, see the fluid module
+#[derive(Clone, Copy)]
+pub struct FluidFlow {
+    // fluid ticks between a change next to it and it flowing, higher is slower
+    pub ticks: u32,
+    // whether a flowing block between two sources (and on top of something) becomes a source itself
+    pub renews: bool,
+}
+
+impl Default for FluidFlow {
+    fn default() -> Self {
+        FluidFlow { ticks: 5, renews: false }
+    }
+}
+
 pub struct BlockData {
     pub name: String,
     pub block_type: BlockType,
-    pub texture_file: String
+    pub shape: BlockShape,
+    pub texture_file: String,
+    // seconds it takes to break by hand outside of creative, negative means it can't be broken
+    pub hardness: f32,
+    // name of the block given when this one is broken outside of creative, None drops nothing
+    pub drop: Option<String>,
+    // each combination of these gets its own state id, with every property at its first value being the default
+    pub properties: Vec<BlockProperty>,
+    // only used by fluids, None flows at the default speed
+    pub flow: Option<FluidFlow>
+}
+
+impl BlockData {
+    pub fn is_breakable(&self) -> bool {
+        self.hardness >= 0.0
+    }
+
+    // how many state ids this block takes up, None past MAX_BLOCK_STATES
+    pub fn checked_state_count(&self) -> Option<u32> {
+        self.properties.iter()
+            .try_fold(1u32, |count, p| count.checked_mul(u32::try_from(p.values.len()).ok()?))
+            .filter(|count| *count <= MAX_BLOCK_STATES)
+    }
+
+    // the builtin blocks are far under the limit and mods are checked when they register a block
+    pub fn state_count(&self) -> u32 {
+        self.checked_state_count().expect("block has more states than MAX_BLOCK_STATES")
+    }
+
+    // which value of each property a state (counted from the block's first state) has
+    // the first property changes fastest
+    pub fn decode_state(&self, index: u32) -> Vec<u32> {
+        let mut rest = index;
+        self.properties.iter().map(|p| {
+            let count = p.values.len() as u32;
+            let value = rest % count;
+            rest /= count;
+            value
+        }).collect()
+    }
+
+    pub fn encode_state(&self, values: &[u32]) -> u32 {
+        let mut index = 0;
+        for (p, value) in self.properties.iter().zip(values).rev() {
+            index = index * p.values.len() as u32 + value;
+        }
+        index
+    }
+
+    // what the properties look like when saved, a block whose properties change gets new state ids
+    pub fn property_signature(&self) -> String {
+        let parts: Vec<String> = self.properties.iter().map(|p| format!("{}={}", p.name, p.values.join(","))).collect();
+        parts.join(";")
+    }
+}
+
+// registers every block in the game
+// do not register air here, the universe init handles that automatically to ensure air is always id 0
+pub fn register_default_blocks(universe: &Universe) {
+    universe.register_block(
+        BlockData {
+            name: String::from("stone"),
+            block_type: BlockType::OpaqueSolid,
+            shape: BlockShape::Cube,
+            texture_file: String::from("textures/block/stone.png"),
+            hardness: 1.5,
+            drop: Some(String::from("stone")),
+            properties: Vec::new(),
+            flow: None
+        },
+    );
+    universe.register_block(
+        BlockData {
+            name: String::from("dirt"),
+            block_type: BlockType::OpaqueSolid,
+            shape: BlockShape::Cube,
+            texture_file: String::from("textures/block/dirt.png"),
+            hardness: 0.5,
+            drop: Some(String::from("dirt")),
+            properties: Vec::new(),
+            flow: None
+        },
+    );
+    universe.register_block(
+        BlockData {
+            name: String::from("stone_slab"),
+            block_type: BlockType::OpaqueSolid,
+            shape: BlockShape::Slab,
+            texture_file: String::from("textures/block/stone.png"),
+            hardness: 1.5,
+            drop: Some(String::from("stone_slab")),
+            properties: vec![BlockProperty::half()],
+            flow: None
+        },
+    );
+    universe.register_block(
+        BlockData {
+            name: String::from("stone_stairs"),
+            block_type: BlockType::OpaqueSolid,
+            shape: BlockShape::Stairs,
+            texture_file: String::from("textures/block/stone.png"),
+            hardness: 1.5,
+            drop: Some(String::from("stone_stairs")),
+            properties: vec![BlockProperty::facing(), BlockProperty::half()],
+            flow: None
+        },
+    );
+    // fluids can't be broken or picked up, you replace them by placing something in them
+    universe.register_block(
+        BlockData {
+            name: String::from("water"),
+            block_type: BlockType::Fluid,
+            shape: BlockShape::Cube,
+            texture_file: String::from("textures/block/water.png"),
+            hardness: -1.0,
+            drop: None,
+            properties: vec![BlockProperty::level(7)],
+            flow: Some(FluidFlow { ticks: 5, renews: true })
+        },
+    );
+    universe.register_block(
+        BlockData {
+            name: String::from("lava"),
+            block_type: BlockType::Fluid,
+            shape: BlockShape::Cube,
+            texture_file: String::from("textures/block/lava.png"),
+            hardness: -1.0,
+            drop: None,
+            properties: vec![BlockProperty::level(3)],
+            flow: Some(FluidFlow { ticks: 30, renews: false })
+        },
+    );
 }
@@ -0,0 +1,222 @@
+// block entities, for blocks that need more than a BlockId (chests, signs, furnaces...)
+// the universe stores them per chunk as serialized bytes keyed by position in the chunk, see the BLOCK ENTITIES part of universe.rs
+// a block entity goes away when its block is replaced with a different block, changing the block's state keeps it
+//
+// on the game's side each kind is a component, registered with register_block_entity
+// while its chunk is loaded a block entity is an ecs entity with a BlockEntityPos and that component,
+// and changes to the component get saved back to the universe
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::chunk::chunk::{split_block_position, BlockId, CHUNK_SIZE_I32};
+use super::universe::Universe;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlockEntityData {
+    // which BlockEntity this is, see BlockEntity::KIND
+    pub kind: String,
+    pub data: Vec<u8>,
+}
+
+// every block entity in one chunk, keyed by position in the chunk
+#[derive(Default, Serialize, Deserialize)]
+pub struct ChunkBlockEntities(pub BTreeMap<[u8; 3], BlockEntityData>);
+
+impl ChunkBlockEntities {
+    pub fn key(local: UVec3) -> [u8; 3] {
+        [local.x as u8, local.y as u8, local.z as u8]
+    }
+
+    // world positions, given where the chunk is
+    pub fn iter(&self, chunk: IVec3) -> impl Iterator<Item = (IVec3, &BlockEntityData)> {
+        let origin = chunk * CHUNK_SIZE_I32;
+        self.0.iter().map(move |([x, y, z], data)| (origin + IVec3::new(*x as i32, *y as i32, *z as i32), data))
+    }
+}
+
+pub trait BlockEntity: Component + Serialize + DeserializeOwned {
+    // saved with the data so it gets loaded back as the right type, has to be unique
+    const KIND: &'static str;
+}
+
+impl Universe {
+    // None if there isn't one, or it's a different kind
+    pub fn get_block_entity<T: BlockEntity>(&self, pos: IVec3) -> Option<T> {
+        let stored = self.block_entity(pos)?;
+        if stored.kind != T::KIND {
+            return None;
+        }
+        bincode::deserialize(&stored.data).ok()
+    }
+
+    pub fn put_block_entity<T: BlockEntity>(&self, pos: IVec3, value: &T) {
+        let data = bincode::serialize(value).expect("Block entities should always serialize");
+        self.set_block_entity(pos, Some(BlockEntityData { kind: String::from(T::KIND), data }));
+    }
+
+    pub fn remove_block_entity(&self, pos: IVec3) {
+        self.set_block_entity(pos, None);
+    }
+}
+
+// where a loaded block entity is
+#[derive(Component, Clone, Copy)]
+pub struct BlockEntityPos(pub IVec3);
+
+#[cfg(feature = "client")]
+pub use ecs::*;
+
+#[cfg(feature = "client")]
+mod ecs {
+    use std::collections::HashMap;
+
+    use bevy::ecs::system::EntityCommands;
+    use bevy::prelude::*;
+
+    use super::{BlockEntity, BlockEntityPos};
+    use crate::chunk::chunk::split_block_position;
+    use crate::world::edits::BlockChangedEvent;
+    use crate::world::loading::{ChunkPosition, UnloadChunkEvent};
+    use crate::world::universe::Universe;
+
+    // inserts the component for a kind, false if the data doesn't deserialize
+    type Loader = fn(&mut EntityCommands, &[u8]) -> bool;
+
+    #[derive(Resource, Default)]
+    pub struct BlockEntityKinds(HashMap<&'static str, Loader>);
+
+    // the ecs entity of every loaded block entity, by chunk and then position
+    #[derive(Resource, Default)]
+    pub struct LoadedBlockEntities(HashMap<IVec3, HashMap<IVec3, Entity>>);
+
+    impl LoadedBlockEntities {
+        pub fn get(&self, pos: IVec3) -> Option<Entity> {
+            let (chunk, _) = split_block_position(pos);
+            self.0.get(&chunk)?.get(&pos).copied()
+        }
+    }
+
+    fn load<T: BlockEntity>(entity: &mut EntityCommands, data: &[u8]) -> bool {
+        match bincode::deserialize::<T>(data) {
+            Ok(value) => {
+                entity.insert(value);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // anything spawned with a BlockEntityPos and a T gets saved, including the ones just loaded
+    // (writing those back is cheap next to everything else loading a chunk does)
+    fn save_block_entities<T: BlockEntity>(
+        changed: Query<(Entity, &BlockEntityPos, &T), Changed<T>>,
+        universe: Res<Universe>,
+        mut loaded: ResMut<LoadedBlockEntities>,
+    ) {
+        for (entity, BlockEntityPos(pos), value) in changed.iter() {
+            universe.put_block_entity(*pos, value);
+            let (chunk, _) = split_block_position(*pos);
+            loaded.0.entry(chunk).or_default().insert(*pos, entity);
+        }
+    }
+
+    pub trait RegisterBlockEntity {
+        fn register_block_entity<T: BlockEntity>(&mut self) -> &mut Self;
+    }
+
+    impl RegisterBlockEntity for App {
+        fn register_block_entity<T: BlockEntity>(&mut self) -> &mut Self {
+            self.init_resource::<BlockEntityKinds>();
+            let mut kinds = self.world_mut().resource_mut::<BlockEntityKinds>();
+            if kinds.0.insert(T::KIND, load::<T>).is_some() {
+                warn!("Block entity kind {} was registered twice", T::KIND);
+            }
+            self.init_resource::<LoadedBlockEntities>()
+                .add_systems(PostUpdate, save_block_entities::<T>)
+        }
+    }
+
+    fn spawn_block_entities(
+        chunks: Query<&ChunkPosition, Added<ChunkPosition>>,
+        universe: Res<Universe>,
+        kinds: Res<BlockEntityKinds>,
+        mut loaded: ResMut<LoadedBlockEntities>,
+        mut commands: Commands,
+    ) {
+        for ChunkPosition(chunk) in chunks.iter() {
+            let stored = universe.chunk_block_entities(chunk);
+            for (pos, data) in stored.iter(*chunk) {
+                let Some(load) = kinds.0.get(data.kind.as_str()) else {
+                    warn!("Unknown block entity kind {} at {} {} {}", data.kind, pos.x, pos.y, pos.z);
+                    continue;
+                };
+                let mut entity = commands.spawn(BlockEntityPos(pos));
+                if !load(&mut entity, &data.data) {
+                    warn!("Couldn't load the {} at {} {} {}", data.kind, pos.x, pos.y, pos.z);
+                    entity.despawn();
+                    continue;
+                }
+                loaded.0.entry(*chunk).or_default().insert(pos, entity.id());
+            }
+        }
+    }
+
+    fn despawn_unloaded(
+        mut ev_unload: EventReader<UnloadChunkEvent>,
+        mut loaded: ResMut<LoadedBlockEntities>,
+        mut commands: Commands,
+    ) {
+        for UnloadChunkEvent(chunk) in ev_unload.read() {
+            for entity in loaded.0.remove(chunk).into_iter().flat_map(|e| e.into_values()) {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    // the universe already dropped the stored data when the block changed, this gets rid of the ecs side
+    fn despawn_removed(
+        mut ev_changed: EventReader<BlockChangedEvent>,
+        universe: Res<Universe>,
+        mut loaded: ResMut<LoadedBlockEntities>,
+        mut commands: Commands,
+    ) {
+        for BlockChangedEvent(edit) in ev_changed.read() {
+            let (chunk, _) = split_block_position(edit.pos);
+            let Some(in_chunk) = loaded.0.get_mut(&chunk) else {
+                continue;
+            };
+            if in_chunk.contains_key(&edit.pos) && universe.block_entity(edit.pos).is_none() {
+                let entity = in_chunk.remove(&edit.pos).unwrap();
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    pub struct BlockEntityPlugin;
+    impl Plugin for BlockEntityPlugin {
+        fn build(&self, app: &mut App) {
+            app.init_resource::<BlockEntityKinds>()
+                .init_resource::<LoadedBlockEntities>()
+                .add_systems(Update, (spawn_block_entities, despawn_unloaded, despawn_removed));
+        }
+    }
+}
+
+// drops the block entities in a chunk whose block was replaced by a different block
+// changes are (position, old block, new block), used by Universe::edit_region
+pub(super) fn forget_replaced(universe: &Universe, entities: &mut ChunkBlockEntities, changes: &[(IVec3, BlockId, BlockId)]) -> bool {
+    let mut forgot = false;
+    for (pos, old, new) in changes {
+        let (_, local) = split_block_position(*pos);
+        let key = ChunkBlockEntities::key(local);
+        if entities.0.contains_key(&key) && universe.base_id(*old) != universe.base_id(*new) {
+            entities.0.remove(&key);
+            forgot = true;
+        }
+    }
+    forgot
+}
@@ -2,6 +2,7 @@ use bevy::prelude::*;
 use crate::chunk::chunk::BlockId;
 use std::collections::HashMap;
 use super::universe::Universe;
+use block_mesh::VoxelVisibility;
 
 #[derive(Resource)]
 pub struct BlockMaterials {
@@ -16,14 +17,22 @@ impl BlockMaterials {
     }
 
     pub fn get_material(&mut self, asset_server : &AssetServer, materials : &mut Assets<StandardMaterial>, universe: &Universe, id: BlockId, variant: u64) -> Handle<StandardMaterial> {
+        // every state of a block looks the same for now
+        let id = universe.base_id(id);
         match self.map.get(&(id, variant)) {
             Some(mat) => mat.clone(),
             None => {
                 let block_data = universe.get_block_data_id(id);
                 let h_img = asset_server.load(block_data.texture_file.clone());
+                // translucent blocks get their own meshes, which bevy draws after everything opaque, back to front
+                let alpha_mode = match block_data.block_type.get_visibility() {
+                    VoxelVisibility::Translucent => AlphaMode::Blend,
+                    _ => AlphaMode::Opaque,
+                };
                 let sm = StandardMaterial {
                     perceptual_roughness: 0.95,
                     base_color_texture: Some(h_img),
+                    alpha_mode,
                     ..default()
                 };
 
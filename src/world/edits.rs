@@ -0,0 +1,77 @@
+// every block change goes through Universe::edit_region, which notes it down along with what caused it
+// once a frame those notes are turned into events, so anything that cares about blocks changing
+// (networking, scripts, and later on lighting or sounds) can read them instead of hooking into whoever made the edit
+
+use bevy::prelude::*;
+
+use crate::chunk::chunk::{BlockId, AIR};
+use super::universe::Universe;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditCause {
+    // this side's player breaking or placing
+    Player,
+    // a player connected to this server, by id
+    RemotePlayer(u32),
+    // console commands and the world edit tools
+    Command,
+    Script,
+    // fluids flowing on their own, see the fluid module
+    Fluid,
+    // the server telling a client what a block is now
+    Server,
+}
+
+#[derive(Clone, Copy)]
+pub struct BlockEdit {
+    pub pos: IVec3,
+    pub old: BlockId,
+    pub new: BlockId,
+    pub cause: EditCause,
+}
+
+// every change
+#[derive(Event, Clone, Copy)]
+pub struct BlockChangedEvent(pub BlockEdit);
+
+// something that wasn't air is gone, this includes being replaced by another block
+#[derive(Event, Clone, Copy)]
+pub struct BlockBrokenEvent(pub BlockEdit);
+
+// something that isn't air is there now, this includes replacing another block
+#[derive(Event, Clone, Copy)]
+pub struct BlockPlacedEvent(pub BlockEdit);
+
+// the universe doesn't keep track of edits until asked to, so tools without an App don't pile them up forever
+fn start_recording_edits(universe: Res<Universe>) {
+    universe.record_edits();
+}
+
+fn send_block_events(
+    universe: Res<Universe>,
+    mut ev_changed: EventWriter<BlockChangedEvent>,
+    mut ev_broken: EventWriter<BlockBrokenEvent>,
+    mut ev_placed: EventWriter<BlockPlacedEvent>,
+) {
+    for edit in universe.take_edits() {
+        if edit.old != AIR {
+            ev_broken.send(BlockBrokenEvent(edit));
+        }
+        if edit.new != AIR {
+            ev_placed.send(BlockPlacedEvent(edit));
+        }
+        ev_changed.send(BlockChangedEvent(edit));
+    }
+}
+
+// expects a Universe to be inserted by whoever adds this
+pub struct BlockEventsPlugin;
+impl Plugin for BlockEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<BlockChangedEvent>()
+            .add_event::<BlockBrokenEvent>()
+            .add_event::<BlockPlacedEvent>()
+            .add_systems(PreStartup, start_recording_edits)
+            .add_systems(PostUpdate, send_block_events);
+    }
+}
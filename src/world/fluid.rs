@@ -0,0 +1,265 @@
+// flowing fluids, water and lava and anything a mod registers with the fluid type
+// how far along a flow a block is lives in its level property, so it's saved in the chunk like any other block state:
+// level 0 is a source, each block a fluid spreads sideways is one level higher, and it stops once it runs out of levels
+// fluid falling down starts again at level 1, and a fluid without a level property just sits there
+//
+// nothing scans the world for fluids, any edit next to one schedules it to update after its flow ticks,
+// and the edits that update makes schedule the blocks around them, so a flow keeps going until it settles
+// (flows that were still going when the game closed stay put until something next to them changes)
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use zerocopy::FromBytes;
+
+use crate::chunk::chunk::{split_block_position, BlockId, Chunk, AIR};
+use super::block::{BlockType, FluidFlow};
+use super::edits::{BlockChangedEvent, EditCause};
+use super::region::Region;
+use super::universe::Universe;
+
+// seconds per fluid tick, independent of the fixed tick rate so fluids flow the same on clients and servers
+const FLUID_TICK: f64 = 0.05;
+// more than this many updates in one tick wait for the next one, so a flood can't stall the game
+const MAX_UPDATES_PER_TICK: usize = 4096;
+
+const NEIGHBOURS: [IVec3; 6] = [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z];
+const HORIZONTAL: [IVec3; 4] = [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct FluidState {
+    // the fluid's first state, the same for every level of it
+    pub fluid: BlockId,
+    pub level: u32,
+    // also how many blocks it spreads sideways
+    pub max_level: u32,
+}
+
+impl FluidState {
+    pub fn is_source(&self) -> bool {
+        self.level == 0
+    }
+
+    // how high the surface is, in blocks, a source isn't quite full so you can see where the water ends
+    pub fn height(&self) -> f32 {
+        let source = 8.0 / 9.0;
+        source * (self.max_level + 1 - self.level) as f32 / (self.max_level + 1) as f32
+    }
+}
+
+// None if the block isn't a fluid
+// levels are the index of the level property's value, so a fluid's level values should count up from 0
+pub fn fluid_state(universe: &Universe, id: BlockId) -> Option<FluidState> {
+    let data = universe.get_block_data_id(id);
+    if !matches!(data.block_type, BlockType::Fluid) {
+        return None;
+    }
+    let fluid = universe.base_id(id);
+    let Some(property) = data.properties.iter().position(|p| p.name == "level") else {
+        return Some(FluidState { fluid, level: 0, max_level: 0 });
+    };
+    Some(FluidState {
+        fluid,
+        level: data.decode_state(id.0 - fluid.0)[property],
+        max_level: data.properties[property].values.len() as u32 - 1,
+    })
+}
+
+fn flow_of(universe: &Universe, fluid: BlockId) -> FluidFlow {
+    universe.get_block_data_id(fluid).flow.unwrap_or_default()
+}
+
+fn with_level(universe: &Universe, fluid: BlockId, level: u32) -> Option<BlockId> {
+    universe.with_property(fluid, "level", &level.to_string())
+}
+
+// positions waiting on an update, with the fluid tick they're due on
+#[derive(Resource, Default)]
+struct FluidUpdates {
+    tick: u64,
+    // time left over from the last fixed tick
+    elapsed: f64,
+    due: HashMap<IVec3, u64>,
+}
+
+fn schedule_fluid_updates(
+    mut ev_changed: EventReader<BlockChangedEvent>,
+    universe: Res<Universe>,
+    mut updates: ResMut<FluidUpdates>,
+) {
+    let tick = updates.tick;
+    // only read from, but it saves fetching the same chunk over and over for a big edit
+    let mut view = FluidTick::new(&universe);
+    for BlockChangedEvent(edit) in ev_changed.read() {
+        for pos in std::iter::once(edit.pos).chain(NEIGHBOURS.map(|d| edit.pos + d)) {
+            let Some(state) = view.fluid_at(pos) else {
+                continue;
+            };
+            let due = tick + flow_of(&universe, state.fluid).ticks as u64;
+            let entry = updates.due.entry(pos).or_insert(due);
+            *entry = (*entry).min(due);
+        }
+    }
+}
+
+// one fluid tick's view of the world
+// each chunk is read once, the first time an update looks at it, and what the updates change is held back
+// so it goes into the universe as one edit per chunk at the end, with later updates seeing what earlier ones did
+struct FluidTick<'a> {
+    universe: &'a Universe,
+    chunks: HashMap<IVec3, Option<Box<Chunk>>>,
+    changes: HashMap<IVec3, HashMap<IVec3, BlockId>>,
+}
+
+impl<'a> FluidTick<'a> {
+    fn new(universe: &'a Universe) -> Self {
+        Self { universe, chunks: HashMap::new(), changes: HashMap::new() }
+    }
+
+    fn block_at(&mut self, pos: IVec3) -> Option<BlockId> {
+        let (chunk, local) = split_block_position(pos);
+        if let Some(block) = self.changes.get(&chunk).and_then(|c| c.get(&pos)) {
+            return Some(*block);
+        }
+        let universe = self.universe;
+        self.chunks.entry(chunk)
+            .or_insert_with(|| universe.fetch_chunk(&chunk).and_then(|c| Chunk::read_from(c.as_ref())).map(Box::new))
+            .as_ref()
+            .map(|c| c.get(local.x, local.y, local.z))
+    }
+
+    fn set_block(&mut self, pos: IVec3, block: BlockId) {
+        let (chunk, _) = split_block_position(pos);
+        self.changes.entry(chunk).or_default().insert(pos, block);
+    }
+
+    fn fluid_at(&mut self, pos: IVec3) -> Option<FluidState> {
+        self.block_at(pos).and_then(|id| fluid_state(self.universe, id))
+    }
+
+    fn same_fluid(&mut self, pos: IVec3, fluid: BlockId) -> Option<FluidState> {
+        self.fluid_at(pos).filter(|f| f.fluid == fluid)
+    }
+
+    fn apply(self) {
+        for changes in self.changes.into_values() {
+            // just the part of the chunk that changed, it's still one read and write of the chunk
+            let min = changes.keys().copied().reduce(IVec3::min).unwrap();
+            let max = changes.keys().copied().reduce(IVec3::max).unwrap();
+            self.universe.edit_region(&Region::from_corners(min, max), EditCause::Fluid, |pos, _| changes.get(&pos).copied());
+        }
+    }
+}
+
+// whether a fluid at this level can go here, which is air or a weaker flow of the same fluid
+// chunks that aren't generated don't get flowed into
+fn can_flow_into(tick: &mut FluidTick, pos: IVec3, fluid: BlockId, level: u32) -> bool {
+    match tick.block_at(pos) {
+        None => false,
+        Some(AIR) => true,
+        Some(_) => tick.same_fluid(pos, fluid).is_some_and(|f| !f.is_source() && f.level > level),
+    }
+}
+
+// the level a flowing block should be at given what's around it, None if nothing feeds it anymore
+fn fed_level(tick: &mut FluidTick, pos: IVec3, state: &FluidState, flow: &FluidFlow) -> Option<u32> {
+    if tick.same_fluid(pos + IVec3::Y, state.fluid).is_some() {
+        return Some(1);
+    }
+    let sides: Vec<FluidState> = HORIZONTAL.iter()
+        .filter_map(|d| tick.same_fluid(pos + *d, state.fluid))
+        .collect();
+
+    if flow.renews && sides.iter().filter(|f| f.is_source()).count() >= 2 {
+        let below = tick.block_at(pos - IVec3::Y);
+        let supported = below.is_some_and(|id| tick.universe.get_block_data_id(id).block_type.is_solid())
+            || tick.same_fluid(pos - IVec3::Y, state.fluid).is_some_and(|f| f.is_source());
+        if supported {
+            return Some(0);
+        }
+    }
+
+    sides.iter()
+        .map(|f| f.level + 1)
+        .min()
+        .filter(|level| *level <= state.max_level)
+}
+
+fn update_fluid(tick: &mut FluidTick, pos: IVec3) {
+    let Some(state) = tick.fluid_at(pos) else {
+        return;
+    };
+    let universe = tick.universe;
+    let flow = flow_of(universe, state.fluid);
+
+    // a flowing block follows whatever feeds it, and drains away once nothing does
+    let mut level = state.level;
+    if !state.is_source() {
+        match fed_level(tick, pos, &state, &flow) {
+            None => {
+                tick.set_block(pos, AIR);
+                return;
+            }
+            Some(fed) if fed != level => {
+                let Some(block) = with_level(universe, state.fluid, fed) else {
+                    return;
+                };
+                tick.set_block(pos, block);
+                level = fed;
+            }
+            Some(_) => {}
+        }
+    }
+
+    // falling comes first, it only spreads sideways once it lands on something
+    let below = pos - IVec3::Y;
+    if can_flow_into(tick, below, state.fluid, 1) {
+        if let Some(block) = with_level(universe, state.fluid, 1) {
+            tick.set_block(below, block);
+        }
+        return;
+    }
+    if tick.same_fluid(below, state.fluid).is_some() || level >= state.max_level {
+        return;
+    }
+    let Some(block) = with_level(universe, state.fluid, level + 1) else {
+        return;
+    };
+    for d in HORIZONTAL {
+        if can_flow_into(tick, pos + d, state.fluid, level + 1) {
+            tick.set_block(pos + d, block);
+        }
+    }
+}
+
+fn update_fluids(time: Res<Time>, universe: Res<Universe>, mut updates: ResMut<FluidUpdates>) {
+    updates.elapsed += time.delta_seconds_f64();
+    while updates.elapsed >= FLUID_TICK {
+        updates.elapsed -= FLUID_TICK;
+        updates.tick += 1;
+
+        let tick = updates.tick;
+        let ready: Vec<IVec3> = updates.due.iter()
+            .filter(|(_, due)| **due <= tick)
+            .map(|(pos, _)| *pos)
+            .take(MAX_UPDATES_PER_TICK)
+            .collect();
+        let mut fluid_tick = FluidTick::new(&universe);
+        for pos in ready {
+            updates.due.remove(&pos);
+            update_fluid(&mut fluid_tick, pos);
+        }
+        fluid_tick.apply();
+    }
+}
+
+// whoever owns the world adds this, a client connected to a server gets told what fluids did like any other edit
+// expects the BlockEventsPlugin too, on the client the chunk loading remeshes what fluids change
+pub struct FluidPlugin;
+impl Plugin for FluidPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FluidUpdates>()
+            .add_systems(FixedUpdate, (schedule_fluid_updates, update_fluids).chain());
+    }
+}
@@ -1,17 +1,22 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use bevy::math::f64::DVec3;
 use bevy::prelude::*;
 use bevy::tasks::*;
-use crate::chunk::chunk::BlockId;
 use crate::chunk::chunk::Chunk;
-use crate::chunk::chunk::CHUNK_SIZE_I32;
-use crate::chunk::mesh::bake;
+use crate::chunk::chunk::{split_block_position, CHUNK_SIZE_I32};
+use crate::chunk::mesh::{bake, ChunkMeshes};
 use crate::settings::Settings;
 use crate::position::universe_transform::UniverseTransform;
 use zerocopy::FromBytes;
 use super::universe::Universe;
+use super::edits::BlockChangedEvent;
 use super::block_materials::BlockMaterials;
 use crate::terrain::terraingen::generate_chunk;
+use crate::net::client::ServerConnection;
 
 #[derive(Component)]
 pub struct ChunkPosition(pub IVec3);
@@ -29,21 +34,78 @@ pub struct UngeneratedChunkBundle {
 #[derive(Event)]
 pub struct GenerateChunkEvent(pub IVec3);
 
+// sent once a freshly generated chunk has been written to the universe
+#[derive(Event)]
+pub struct ChunkGeneratedEvent(pub IVec3);
+
+// set by a task once the task pool actually gets to it, until then the chunk is just queued
+#[derive(Clone, Default)]
+pub struct TaskStarted(Arc<AtomicBool>);
+
+impl TaskStarted {
+    fn mark(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// resolves to how long generating took
 #[derive(Component)]
-pub struct GenerateChunkTask(pub Task<()>);
+pub struct GenerateChunkTask(pub Task<Duration>, pub TaskStarted);
+
+// how many triangles a chunk's meshes have, for the debug overlay
+#[derive(Component, Default)]
+pub struct ChunkTriangles(pub u32);
+
+// recent timings from the chunk pipeline, for the debug overlay
+#[derive(Resource, Default)]
+pub struct ChunkPipelineStats {
+    pub generate_times: VecDeque<Duration>,
+    pub bake_times: VecDeque<Duration>,
+    pub generated: u64,
+    pub baked: u64,
+    // vertices handed to the renderer so far
+    pub vertices_uploaded: u64,
+}
+
+const STAT_SAMPLES: usize = 128;
+
+impl ChunkPipelineStats {
+    fn record(samples: &mut VecDeque<Duration>, time: Duration) {
+        if samples.len() == STAT_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(time);
+    }
+
+    // over the last STAT_SAMPLES chunks
+    pub fn average(samples: &VecDeque<Duration>) -> Option<Duration> {
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().sum::<Duration>() / samples.len() as u32)
+    }
+}
 
 #[derive(Component)]
 pub struct MeshPosition(pub IVec3);
 
+// translucent meshes are centered on their chunk, see ChunkMeshes
+#[derive(Component)]
+pub struct TranslucentMesh;
+
 #[derive(Resource)]
 pub struct ChunkEntityMap(HashMap<IVec3, Entity>);
 
 impl MeshPosition {
-    pub fn to_render_transform(&self, origin: &UniverseTransform, out: &mut Transform) {
+    pub fn to_render_transform(&self, origin: DVec3, out: &mut Transform) {
         // i hate casting
-        let x = (((self.0.x * CHUNK_SIZE_I32) as f64) - origin.loc.position.x) as f32;
-        let y = (((self.0.y * CHUNK_SIZE_I32) as f64) - origin.loc.position.y) as f32;
-        let z = (((self.0.z * CHUNK_SIZE_I32) as f64) - origin.loc.position.z) as f32;
+        let x = (((self.0.x * CHUNK_SIZE_I32) as f64) - origin.x) as f32;
+        let y = (((self.0.y * CHUNK_SIZE_I32) as f64) - origin.y) as f32;
+        let z = (((self.0.z * CHUNK_SIZE_I32) as f64) - origin.z) as f32;
         out.translation = Vec3::new(x, y, z);
     }
 }
@@ -59,15 +121,20 @@ fn on_generate_chunk(
         let coords = ev.0;
         // debug!("generating {} {} {}", coords.x, coords.y, coords.z);
         let u = (*universe.as_ref()).clone();
+        let started = TaskStarted::default();
+        let s = started.clone();
         commands.spawn(
             UngeneratedChunkBundle {
                 chunk_position: ChunkPosition(coords),
                 meshes: ChunkMeshList(Vec::new()), 
                 task: GenerateChunkTask(task_pool.spawn(async move {
+                    s.mark();
+                    let start = Instant::now();
                     let c = generate_chunk(&u, coords);
                     u.flush_chunk(&coords, &c);
                     debug!("flushed chunk {} {} {}", coords.x, coords.y, coords.z);
-                }))});
+                    start.elapsed()
+                }), started)});
     }
 }
 
@@ -75,11 +142,16 @@ fn finish_generating_tasks(
     mut chunk_query: Query<(Entity, &ChunkPosition, &mut GenerateChunkTask)>,
     mut commands: Commands,
     mut ev_remesh: EventWriter<ChunkRemeshEvent>,
-    mut chunk_entity_map: ResMut<ChunkEntityMap>
+    mut ev_generated: EventWriter<ChunkGeneratedEvent>,
+    mut chunk_entity_map: ResMut<ChunkEntityMap>,
+    mut stats: ResMut<ChunkPipelineStats>
 ) {
     chunk_query.iter_mut()
-        .for_each(|(entity, ChunkPosition(pos), task)| {
-        if task.0.is_finished() {
+        .for_each(|(entity, ChunkPosition(pos), mut task)| {
+        if let Some(time) = block_on(poll_once(&mut task.0)) {
+            ChunkPipelineStats::record(&mut stats.generate_times, time);
+            stats.generated += 1;
+
             // delete the task and get the entity id
             let ce = commands.entity(entity)
                     .remove::<GenerateChunkTask>()
@@ -89,7 +161,7 @@ fn finish_generating_tasks(
             chunk_entity_map.0.insert(*pos, ce);
             // fire remesh event
             ev_remesh.send(ChunkRemeshEvent(*pos));
-            
+            ev_generated.send(ChunkGeneratedEvent(*pos));
         }
     });
 }
@@ -98,8 +170,9 @@ fn finish_generating_tasks(
 pub struct ChunkRemeshEvent(pub IVec3);
 
 
+// resolves to the new meshes and how long baking them took
 #[derive(Component)]
-pub struct ChunkRemeshTask(Task<HashMap<BlockId, Mesh>>);
+pub struct ChunkRemeshTask(Task<(ChunkMeshes, Duration)>, pub TaskStarted);
 
 
 fn on_chunk_remesh(
@@ -111,20 +184,27 @@ fn on_chunk_remesh(
     let task_pool = AsyncComputeTaskPool::get();
 
     for ChunkRemeshEvent(pos) in ev_remesh.read() {
-        let e = chunk_entity_map.0.get(pos).unwrap();
+        // edits can touch chunks that aren't loaded, those get meshed whenever they are loaded next
+        let Some(e) = chunk_entity_map.0.get(pos) else {
+            continue;
+        };
         let u = (*universe.as_ref()).clone();
         let c = u.fetch_chunk_exists(pos);
+        let started = TaskStarted::default();
+        let s = started.clone();
         //let p = pos.clone();
         commands.entity(*e).insert(
             ChunkRemeshTask(task_pool.spawn(async move {
+                s.mark();
+                let start = Instant::now();
                 //debug!("remeshing {} {} {}", p.x, p.y, p.z);
                 let mm = bake(
                     &u,
                     Chunk::ref_from(c.as_ref()).unwrap()
                 );
                 //debug!("done remeshing {} {} {}", p.x, p.y, p.z);
-                mm
-            }))
+                (mm, start.elapsed())
+            }), started)
         );
     }
 }
@@ -138,11 +218,23 @@ fn finish_remeshing_tasks(
     universe: Res<Universe>,
     //player: Query<&WorldPosition, With<ThisPlayer>>,
     asset_server: Res<AssetServer>,
+    mut stats: ResMut<ChunkPipelineStats>,
 ) {
     //let pwp = player.single();
     chunk_query.iter_mut()
         .for_each(|(entity, mut mesh_list, ChunkPosition(pos), mut task)| {
-            if let Some(new_meshes) = block_on(poll_once(&mut task.0)) {
+            if let Some((new_meshes, time)) = block_on(poll_once(&mut task.0)) {
+                ChunkPipelineStats::record(&mut stats.bake_times, time);
+                stats.baked += 1;
+                let all_meshes = || new_meshes.opaque.values().chain(new_meshes.translucent.values());
+                let triangles: usize = all_meshes()
+                    .filter_map(|m| m.indices())
+                    .map(|i| i.len() / 3)
+                    .sum();
+                stats.vertices_uploaded += all_meshes()
+                    .map(|m| m.count_vertices() as u64)
+                    .sum::<u64>();
+
                 // delete all previous meshes
                 // does despawning the entity automatically unload the mesh asset in Assets<Mesh>?
                 // is that something we need to worry about?
@@ -151,20 +243,26 @@ fn finish_remeshing_tasks(
                     commands.entity(m).despawn();
                 }
 
-                for (bid, mesh) in new_meshes {
+                let ChunkMeshes { opaque, translucent } = new_meshes;
+                for (bid, mesh, is_translucent) in opaque.into_iter().map(|(b, m)| (b, m, false))
+                    .chain(translucent.into_iter().map(|(b, m)| (b, m, true))) {
                     let mat = block_materials.get_material(asset_server.as_ref(), material_assets.as_mut(), &universe, bid, 0,);
                     //wp.to_render_transform(pwp, &mut trans);
-                    let e = commands
-                        .spawn(PbrBundle {
-                            mesh: mesh_assets.add(mesh),
-                            material: mat.clone(),
-                            ..default()
-                        })
-                        .insert((MeshPosition(*pos), Transform::from_xyz(0.0,0.0,0.0))).id();
-                    mesh_list.0.push(e);
+                    let mut e = commands.spawn(PbrBundle {
+                        mesh: mesh_assets.add(mesh),
+                        material: mat.clone(),
+                        ..default()
+                    });
+                    e.insert((MeshPosition(*pos), Transform::from_xyz(0.0,0.0,0.0)));
+                    if is_translucent {
+                        e.insert(TranslucentMesh);
+                    }
+                    mesh_list.0.push(e.id());
                 }
                 // update the mesh list
-                commands.entity(entity).remove::<ChunkRemeshTask>();
+                commands.entity(entity)
+                    .remove::<ChunkRemeshTask>()
+                    .insert(ChunkTriangles(triangles as u32));
             }
         })
 }
@@ -178,11 +276,19 @@ fn on_load_chunk(
     mut ev_gen: EventWriter<GenerateChunkEvent>,
     mut commands : Commands,
     mut chunk_entity_map: ResMut<ChunkEntityMap>,
-    universe: Res<Universe>
+    universe: Res<Universe>,
+    server: Option<Res<ServerConnection>>
 ) {
     for ev in ev_load.read() {
         let coords = ev.0;
-        if universe.chunk_generated(&coords) {
+        if server.is_some() && !universe.chunk_generated(&coords) {
+            // the server generates chunks, this one gets meshed once it arrives
+            let e = commands.spawn((
+                ChunkPosition(coords),
+                ChunkMeshList(vec![])
+            )).id();
+            chunk_entity_map.0.insert(coords, e);
+        } else if universe.chunk_generated(&coords) {
             // if the chunk was already generated, just spawn the entity and send a remesh event
             let e = commands.spawn((
                 ChunkPosition(coords),
@@ -260,29 +366,66 @@ fn chunk_loading_manager(
     }
 
     // check if new chunks need to be loaded
-    // stupid dumb algorithm for doing this, in the future we want this to be a spiral to ensure the chunk you're in always goes into the task pool first
+    let mut to_load = Vec::new();
     for dx in -horiz_rd..=horiz_rd {
         for dz in -horiz_rd..=horiz_rd {
             for dy in -vertical_rd..=vertical_rd {
                 let coords = IVec3::new(dx,dy,dz) + player_chunk;
                 if !already_loaded.contains(&coords) {
-                    info!("Loading chunk {},{},{}", coords.x, coords.y, coords.z);
-                    ev_load.send(LoadChunkEvent(coords));
+                    to_load.push(coords);
                 }
             }
         }
     }
+
+    // one ring at a time starting from the player, so the chunk you're in always goes into the task pool first
+    // this matters most when the render distance is turned up and a lot of chunks show up at once
+    to_load.sort_by_key(|c| (*c - player_chunk).abs().max_element());
+    for coords in to_load {
+        info!("Loading chunk {},{},{}", coords.x, coords.y, coords.z);
+        ev_load.send(LoadChunkEvent(coords));
+    }
 }
 
 
-pub fn translate_all_mesh_transforms(
-    mut to_move: Query<(&mut Transform, &MeshPosition)>,
-    player: Query<&UniverseTransform, With<ThisPlayer>>,
+// the one place edits get remeshed, whatever made them
+// a block on the border of its chunk shows up in the meshes of the chunks next to it too
+fn remesh_changed_chunks(
+    mut ev_changed: EventReader<BlockChangedEvent>,
+    mut ev_remesh: EventWriter<ChunkRemeshEvent>,
 ) {
-    let player_world_position = player.single();
+    let last = CHUNK_SIZE_I32 as u32 - 1;
+    let around = |l: u32| match l {
+        0 => -1..=0,
+        l if l == last => 0..=1,
+        _ => 0..=0,
+    };
+
+    let mut chunks: HashSet<IVec3> = HashSet::new();
+    for BlockChangedEvent(edit) in ev_changed.read() {
+        let (chunk, local) = split_block_position(edit.pos);
+        for dx in around(local.x) {
+            for dy in around(local.y) {
+                for dz in around(local.z) {
+                    chunks.insert(chunk + IVec3::new(dx, dy, dz));
+                }
+            }
+        }
+    }
+    for chunk in chunks {
+        ev_remesh.send(ChunkRemeshEvent(chunk));
+    }
+}
 
-    for (transform, mesh_position) in to_move.iter_mut() {
-        mesh_position.to_render_transform(player_world_position, transform.into_inner());
+pub fn translate_all_mesh_transforms(
+    mut to_move: Query<(&mut Transform, &MeshPosition, Has<TranslucentMesh>)>,
+    origin: Res<RenderOrigin>,
+) {
+    for (mut transform, mesh_position, centered) in to_move.iter_mut() {
+        mesh_position.to_render_transform(origin.0, &mut transform);
+        if centered {
+            transform.translation += Vec3::splat((CHUNK_SIZE_I32 / 2) as f32);
+        }
     }
 }
 
@@ -292,14 +435,17 @@ pub struct ChunkEventsPlugin;
 impl Plugin for ChunkEventsPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ChunkEntityMap(HashMap::new()))
+           .init_resource::<ChunkPipelineStats>()
            .add_systems(Update, (
                 chunk_loading_manager,
                 (on_load_chunk, on_unload_chunk),
                 (finish_generating_tasks, on_generate_chunk).chain(),
+                remesh_changed_chunks,
                 (finish_remeshing_tasks,on_chunk_remesh).chain(),
                 translate_all_mesh_transforms
                 ).chain())
            .add_event::<GenerateChunkEvent>()
+           .add_event::<ChunkGeneratedEvent>()
            .add_event::<ChunkRemeshEvent>()
            .add_event::<LoadChunkEvent>()
            .add_event::<UnloadChunkEvent>();
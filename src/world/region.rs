@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use crate::chunk::chunk::{BlockId, AIR};
+
+// an axis aligned box of blocks, both corners inclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Region {
+    pub min: IVec3,
+    pub max: IVec3,
+}
+
+impl Region {
+    // corners can be given in any order
+    pub fn from_corners(a: IVec3, b: IVec3) -> Self {
+        Region {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    pub fn size(&self) -> IVec3 {
+        self.max - self.min + IVec3::ONE
+    }
+
+    pub fn volume(&self) -> usize {
+        let s = self.size();
+        s.x as usize * s.y as usize * s.z as usize
+    }
+
+    pub fn contains(&self, pos: IVec3) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+
+    // whether a position is on the outer layer of the region
+    pub fn on_shell(&self, pos: IVec3) -> bool {
+        self.contains(pos) && (pos.cmpeq(self.min).any() || pos.cmpeq(self.max).any())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = IVec3> {
+        let (min, max) = (self.min, self.max);
+        (min.y..=max.y).flat_map(move |y| {
+            (min.z..=max.z).flat_map(move |z| {
+                (min.x..=max.x).map(move |x| IVec3::new(x, y, z))
+            })
+        })
+    }
+}
+
+// a copied box of blocks, not attached to any position in the world
+// blocks are stored x first, then z, then y (same as sponge schematics)
+#[derive(Clone)]
+pub struct Clipboard {
+    pub size: IVec3,
+    pub blocks: Vec<BlockId>,
+}
+
+impl Clipboard {
+    pub fn new(size: IVec3) -> Self {
+        let size = size.max(IVec3::ZERO);
+        Clipboard {
+            size,
+            blocks: vec![AIR; (size.x * size.y * size.z) as usize],
+        }
+    }
+
+    pub fn index(&self, pos: IVec3) -> usize {
+        (pos.x + pos.z * self.size.x + pos.y * self.size.x * self.size.z) as usize
+    }
+
+    pub fn get(&self, pos: IVec3) -> BlockId {
+        self.blocks[self.index(pos)]
+    }
+
+    pub fn set(&mut self, pos: IVec3, block: BlockId) {
+        let i = self.index(pos);
+        self.blocks[i] = block;
+    }
+
+    // the region this clipboard would cover if pasted with its minimum corner at origin
+    pub fn region_at(&self, origin: IVec3) -> Region {
+        Region {
+            min: origin,
+            max: origin + self.size - IVec3::ONE,
+        }
+    }
+
+    // rotate 90° clockwise around the Y axis (looking down), the given number of times
+    pub fn rotate_y(&self, quarter_turns: u32) -> Clipboard {
+        let mut current = self.clone();
+        for _ in 0..(quarter_turns % 4) {
+            let old = current;
+            let mut new = Clipboard::new(IVec3::new(old.size.z, old.size.y, old.size.x));
+            for pos in old.region_at(IVec3::ZERO).iter() {
+                // +X (north) turns into +Z (east), see the table in universe_transform.rs
+                let rotated = IVec3::new(old.size.z - 1 - pos.z, pos.y, pos.x);
+                new.set(rotated, old.get(pos));
+            }
+            current = new;
+        }
+        current
+    }
+
+    // flip along the given axis (0 = X, 1 = Y, 2 = Z)
+    pub fn mirror(&self, axis: usize) -> Clipboard {
+        let mut new = Clipboard::new(self.size);
+        for pos in self.region_at(IVec3::ZERO).iter() {
+            let mut mirrored = pos;
+            mirrored[axis] = self.size[axis] - 1 - pos[axis];
+            new.set(mirrored, self.get(pos));
+        }
+        new
+    }
+}
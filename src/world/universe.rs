@@ -2,18 +2,29 @@
 
 use crate::chunk::chunk::BlockId;
 use crate::chunk::chunk::Chunk;
+use crate::chunk::chunk::{split_block_position, AIR, CHUNK_SIZE_I32};
 use crate::terrain::noise::DimensionNoise;
 use crate::world::block::BlockData;
 use crate::world::block::BlockType;
+use crate::world::block::{BlockShape, ShapeBox};
 use crate::position::universe_location::UniverseLocation;
+use crate::world::region::{Clipboard, Region};
+use crate::world::edits::{BlockEdit, EditCause};
+use crate::world::block_entity::{forget_replaced, BlockEntityData, ChunkBlockEntities};
+use crate::position::raycast::{ray_box_hit, RaycastHit, VoxelRaycast};
+use crate::position::universe_transform::UniverseTransform;
 use bevy::prelude::*;
+use bevy_math::CompassQuadrant;
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use sled;
 use sled::Tree;
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use std::collections::{HashMap, HashSet};
 use sled::IVec;
 
 use zerocopy::{AsBytes, FromBytes, FromZeroes};
@@ -26,22 +37,57 @@ pub struct Coords {
     z: i32
 }
 
+// a block's entry in the block table, followed by its property signature
+#[derive(AsBytes, FromBytes, FromZeroes)]
+#[repr(C)]
+struct StoredBlock {
+    base: u32,
+    states: u32
+}
+
+// running totals of how long chunk reads and writes take, shared by every clone of the Universe
+#[derive(Default)]
+struct DbTimings {
+    reads: AtomicU64,
+    read_nanos: AtomicU64,
+    writes: AtomicU64,
+    write_nanos: AtomicU64,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct DbTimingTotals {
+    pub reads: u64,
+    pub read_nanos: u64,
+    pub writes: u64,
+    pub write_nanos: u64,
+}
+
 #[derive(Resource, Clone)]
 pub struct Universe {
     db: sled::Db,
+    db_timings: Arc<DbTimings>,
+    // edits since the last take_edits, None until record_edits is called
+    edits: Arc<Mutex<Option<Vec<BlockEdit>>>>,
     pub seed: u64,
     pub dimension_noise : DimensionNoise,
     block_registry_idmap: Arc<RwLock<HashMap<String, BlockId>>>,
     block_registry_datamap: Arc<RwLock<HashMap<BlockId, Arc<BlockData>>>>
 }
 
+// stands in for ids nothing is registered as anymore, like a block whose properties changed since a chunk was saved
+pub const UNKNOWN_BLOCK: &str = "unknown";
+
 fn new_registry<T, U>() -> Arc<RwLock<HashMap<T, U>>> {
     Arc::new(RwLock::new(HashMap::new()))
 }
 
 impl Universe {
     pub fn new() -> Self {
-        let path = env::temp_dir().join("chunkworld");
+        Self::open(env::temp_dir().join("chunkworld"))
+    }
+
+    // a world somewhere other than the usual place, so benchmarks don't trample the one you play in
+    pub fn open(path: PathBuf) -> Self {
         println!("{:?}", path);
         let u = Universe {
             db: sled::Config::default()
@@ -50,6 +96,8 @@ impl Universe {
                 .compression_factor(5)
                 .mode(sled::Mode::HighThroughput)
                 .open().unwrap(),
+            db_timings: Arc::new(DbTimings::default()),
+            edits: Arc::new(Mutex::new(None)),
 
             seed: 0,
             dimension_noise: DimensionNoise::new(0),
@@ -62,12 +110,42 @@ impl Universe {
         u.register_block(BlockData {
             name: String::from("air"), 
             block_type: BlockType::Empty,
-            texture_file: String::from("")
+            shape: BlockShape::Cube,
+            texture_file: String::from(""),
+            hardness: 0.0,
+            drop: None,
+            properties: Vec::new(),
+            flow: None
+        });
+        u.register_block(BlockData {
+            name: String::from(UNKNOWN_BLOCK),
+            block_type: BlockType::OpaqueSolid,
+            shape: BlockShape::Cube,
+            texture_file: String::from("textures/block/unknown.png"),
+            hardness: 1.0,
+            drop: None,
+            properties: Vec::new(),
+            flow: None
         });
 
         u
     }
 
+    // how much space the world database takes up, for the debug overlay
+    pub fn size_on_disk(&self) -> Option<u64> {
+        self.db.size_on_disk().ok()
+    }
+
+    pub fn db_timing_totals(&self) -> DbTimingTotals {
+        let t = &self.db_timings;
+        DbTimingTotals {
+            reads: t.reads.load(Ordering::Relaxed),
+            read_nanos: t.read_nanos.load(Ordering::Relaxed),
+            writes: t.writes.load(Ordering::Relaxed),
+            write_nanos: t.write_nanos.load(Ordering::Relaxed),
+        }
+    }
+
     // CHUNK HANDLING
     pub fn dimension(&self, dim : &str) -> Tree {
         self.db.open_tree(&format!("dim:{}", dim))
@@ -81,9 +159,12 @@ impl Universe {
         };
         let ser_coords = coords.as_bytes();
         let ser_chunk = chunk.as_bytes();
+        let start = Instant::now();
         self.dimension("overworld")
             .insert(ser_coords, ser_chunk)
             .expect("Sled DB failed to insert");
+        self.db_timings.writes.fetch_add(1, Ordering::Relaxed);
+        self.db_timings.write_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
     }
 
     pub fn fetch_chunk(
@@ -97,15 +178,13 @@ impl Universe {
         };
         let dim = self.dimension("overworld");
         let key = coords.as_bytes();
-        if !dim.contains_key(key)
-            .expect("Sled DB failed to query for existence of key") {
-            None
-        } else {
-            let val = dim.get(key)
-                .expect("Sled DB encountered error")
-                .expect("Chunk should be generated if not already present before this");
-            Some(val)
-        }
+        let start = Instant::now();
+        // a single get does the job of the contains_key + get this used to be, and only looks the key up once
+        let val = dim.get(key)
+            .expect("Sled DB encountered error");
+        self.db_timings.reads.fetch_add(1, Ordering::Relaxed);
+        self.db_timings.read_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+        val
     }
 
     pub fn fetch_chunk_exists(
@@ -131,23 +210,137 @@ impl Universe {
             .expect("Sled DB failed to query for existence of key")
     }
 
+    // BLOCK ENTITIES
+    // stored in a tree of their own, one entry per chunk that has any, see the block_entity module
+
+    fn block_entity_tree(&self) -> Tree {
+        self.db.open_tree("block_entities:overworld")
+            .expect("Could not load block entities")
+    }
+
+    pub fn chunk_block_entities(&self, chunk: &IVec3) -> ChunkBlockEntities {
+        let coords = Coords { x: chunk.x, y: chunk.y, z: chunk.z };
+        self.block_entity_tree()
+            .get(coords.as_bytes())
+            .expect("Sled DB encountered error")
+            .and_then(|stored| bincode::deserialize(&stored).ok())
+            .unwrap_or_default()
+    }
+
+    fn store_chunk_block_entities(&self, chunk: &IVec3, entities: &ChunkBlockEntities) {
+        let coords = Coords { x: chunk.x, y: chunk.y, z: chunk.z };
+        let tree = self.block_entity_tree();
+        if entities.0.is_empty() {
+            tree.remove(coords.as_bytes()).expect("Sled DB failed to remove");
+        } else {
+            let stored = bincode::serialize(entities).expect("Block entities should always serialize");
+            tree.insert(coords.as_bytes(), stored).expect("Sled DB failed to insert");
+        }
+    }
+
+    pub fn block_entity(&self, pos: IVec3) -> Option<BlockEntityData> {
+        let (chunk, local) = split_block_position(pos);
+        self.chunk_block_entities(&chunk).0.remove(&ChunkBlockEntities::key(local))
+    }
+
+    // None removes it
+    pub fn set_block_entity(&self, pos: IVec3, data: Option<BlockEntityData>) {
+        let (chunk, local) = split_block_position(pos);
+        let mut entities = self.chunk_block_entities(&chunk);
+        let key = ChunkBlockEntities::key(local);
+        match data {
+            Some(data) => entities.0.insert(key, data),
+            None => entities.0.remove(&key),
+        };
+        self.store_chunk_block_entities(&chunk, &entities);
+    }
+
+    // for when a chunk is thrown away and generated again
+    pub fn clear_block_entities(&self, chunk: &IVec3) {
+        self.store_chunk_block_entities(chunk, &ChunkBlockEntities::default());
+    }
+
+    // PLAYER DATA
+    // players are stored as raw bytes keyed by name, the player module decides what's in them
+    pub fn save_player(&self, name: &str, data: &[u8]) {
+        self.db.open_tree("players")
+            .expect("Could not load player data")
+            .insert(name.as_bytes(), data)
+            .expect("Sled DB failed to insert");
+    }
+
+    pub fn load_player(&self, name: &str) -> Option<IVec> {
+        self.db.open_tree("players")
+            .expect("Could not load player data")
+            .get(name.as_bytes())
+            .expect("Sled DB encountered error")
+    }
+
     // BLOCK REGISTRY THINGS
-    pub fn register_block(&self, block : BlockData) {
+    // a block takes up one id per state, starting at its base id, which is also its default state
+    // which ids each block got is saved in the "blocks" tree, so a block keeps its ids even if others are added or removed
+
+    fn block_table(&self) -> Tree {
+        self.db.open_tree("blocks")
+            .expect("Could not load block table")
+    }
+
+    // where the block's states went last time, if they're still free and the block's properties haven't changed
+    fn stored_base_id(&self, table: &Tree, block: &BlockData, taken: &HashMap<BlockId, Arc<BlockData>>) -> Option<BlockId> {
+        let stored = table.get(block.name.as_bytes()).expect("Sled DB encountered error")?;
+        let header = StoredBlock::read_from_prefix(stored.as_ref())?;
+        let signature = &stored[std::mem::size_of::<StoredBlock>()..];
+        let fits = header.states == block.state_count()
+            && signature == block.property_signature().as_bytes()
+            && (header.base..header.base + header.states).all(|id| !taken.contains_key(&BlockId(id)));
+        fits.then_some(BlockId(header.base))
+    }
+
+    // past every state in use now or saved before
+    fn next_free_id(&self, table: &Tree, taken: &HashMap<BlockId, Arc<BlockData>>) -> BlockId {
+        let saved = table.iter()
+            .values()
+            .filter_map(|v| v.ok())
+            .filter_map(|v| StoredBlock::read_from_prefix(v.as_ref()))
+            .map(|b| b.base + b.states)
+            .max()
+            .unwrap_or(0);
+        let used = taken.keys().map(|id| id.0 + 1).max().unwrap_or(0);
+        BlockId(saved.max(used))
+    }
+
+    pub fn register_block(&self, block : BlockData) -> BlockId {
         let mut id_map = self.block_registry_idmap.write();
         let mut data_map = self.block_registry_datamap.write();
+        let table = self.block_table();
+
+        let base = match self.stored_base_id(&table, &block, &data_map) {
+            Some(base) => base,
+            None => {
+                let base = self.next_free_id(&table, &data_map);
+                let header = StoredBlock { base: base.0, states: block.state_count() };
+                let mut stored = header.as_bytes().to_vec();
+                stored.extend_from_slice(block.property_signature().as_bytes());
+                table.insert(block.name.as_bytes(), stored).expect("Sled DB failed to insert");
+                base
+            }
+        };
 
-        let new_id = BlockId(data_map.len() as u32);
-        id_map.insert(block.name.clone(), new_id);
-        data_map.insert(new_id, Arc::new(block));
-        
-        // todo sync to file on write
+        let states = block.state_count();
+        id_map.insert(block.name.clone(), base);
+        let block = Arc::new(block);
+        for id in base.0..base.0 + states {
+            data_map.insert(BlockId(id), block.clone());
+        }
+        base
     }
 
+    // works for any state of the block, an id nothing has is the unknown block
     pub fn get_block_data_id(&self, id: BlockId) -> Arc<BlockData> {
-        self.block_registry_datamap.read()
-                                   .get(&id)
-                                   .expect(&format!("Failed to get block data for id {}", id.0))
-                                   .clone()
+        if let Some(data) = self.block_registry_datamap.read().get(&id) {
+            return data.clone();
+        }
+        self.get_block_data_name(String::from(UNKNOWN_BLOCK))
     }
 
     pub fn get_block_data_name(&self, name: String) -> Arc<BlockData> {
@@ -157,6 +350,7 @@ impl Universe {
         self.get_block_data_id(*id)
     }
 
+    // the default state
     pub fn block_id_from_name(&self, name: String) -> BlockId {
         self.block_registry_idmap.read()
                 .get(&name)
@@ -164,6 +358,124 @@ impl Universe {
                 .clone()
     }
 
+    // same as above but for user input, where a bad name shouldn't crash the game
+    // also takes a state written out like "log[axis=x]", any property left out is at its default
+    pub fn try_block_id_from_name(&self, name: &str) -> Option<BlockId> {
+        let Some((name, rest)) = name.split_once('[') else {
+            return self.block_registry_idmap.read().get(name).copied();
+        };
+        let properties: Vec<(&str, &str)> = rest.strip_suffix(']')?
+            .split(',')
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| p.split_once('=').map(|(k, v)| (k.trim(), v.trim())))
+            .collect::<Option<_>>()?;
+        self.block_state(name, &properties)
+    }
+
+    // None if the block doesn't exist, or doesn't have one of the properties or values
+    pub fn block_state(&self, name: &str, properties: &[(&str, &str)]) -> Option<BlockId> {
+        let base = self.try_block_id_from_name(name.split('[').next()?)?;
+        let mut state = base;
+        for (property, value) in properties {
+            state = self.with_property(state, property, value)?;
+        }
+        Some(state)
+    }
+
+    pub fn is_known_block(&self, id: BlockId) -> bool {
+        self.block_registry_datamap.read().contains_key(&id)
+    }
+
+    // the default state of whatever block this is a state of
+    pub fn base_id(&self, id: BlockId) -> BlockId {
+        let data = self.get_block_data_id(id);
+        self.block_id_from_name(data.name.clone())
+    }
+
+    // every property of the state, with the value it has
+    pub fn block_properties(&self, id: BlockId) -> Vec<(String, String)> {
+        if !self.is_known_block(id) {
+            return Vec::new();
+        }
+        let data = self.get_block_data_id(id);
+        let index = id.0 - self.base_id(id).0;
+        data.properties.iter()
+            .zip(data.decode_state(index))
+            .map(|(p, v)| (p.name.clone(), p.values[v as usize].clone()))
+            .collect()
+    }
+
+    pub fn block_property(&self, id: BlockId, property: &str) -> Option<String> {
+        self.block_properties(id).into_iter().find(|(p, _)| p == property).map(|(_, v)| v)
+    }
+
+    // the same block with one property changed, None if it doesn't have that property or value
+    pub fn with_property(&self, id: BlockId, property: &str, value: &str) -> Option<BlockId> {
+        if !self.is_known_block(id) {
+            return None;
+        }
+        let data = self.get_block_data_id(id);
+        let base = self.base_id(id);
+        let mut values = data.decode_state(id.0 - base.0);
+        let i = data.properties.iter().position(|p| p.name == property)?;
+        values[i] = data.properties[i].index_of(value)?;
+        Some(BlockId(base.0 + data.encode_state(&values)))
+    }
+
+    // like "log[axis=x]", or just the name for a block without properties
+    pub fn block_state_name(&self, id: BlockId) -> String {
+        let name = self.get_block_data_id(id).name.clone();
+        let properties = self.block_properties(id);
+        if properties.is_empty() {
+            return name;
+        }
+        let parts: Vec<String> = properties.iter().map(|(p, v)| format!("{}={}", p, v)).collect();
+        format!("{}[{}]", name, parts.join(","))
+    }
+
+    // the state a block gets when placed against hit by someone at from
+    // facing is the way they're looking, axis is along the face's normal and half is which half of the face was hit
+    pub fn placement_state(&self, block: BlockId, from: &UniverseTransform, hit: &RaycastHit) -> BlockId {
+        let facing = match from.get_compass_quadrant() {
+            CompassQuadrant::North => "north",
+            CompassQuadrant::East => "east",
+            CompassQuadrant::South => "south",
+            CompassQuadrant::West => "west",
+        };
+        let axis = if hit.normal.x != 0 {
+            "x"
+        } else if hit.normal.z != 0 {
+            "z"
+        } else {
+            "y"
+        };
+        let top = match hit.normal.y {
+            -1 => true,
+            1 => false,
+            _ => hit.point.y - hit.point.y.floor() > 0.5,
+        };
+        let half = if top { "top" } else { "bottom" };
+
+        let mut state = block;
+        for (property, value) in [("facing", facing), ("axis", axis), ("half", half)] {
+            state = self.with_property(state, property, value).unwrap_or(state);
+        }
+        state
+    }
+
+    // every registered block (its default state), in id order
+    pub fn registered_blocks(&self) -> Vec<(BlockId, Arc<BlockData>)> {
+        // same locking order as register_block
+        let id_map = self.block_registry_idmap.read();
+        let data_map = self.block_registry_datamap.read();
+        let mut blocks: Vec<_> = id_map
+            .values()
+            .map(|id| (*id, data_map[id].clone()))
+            .collect();
+        blocks.sort_by_key(|(id, _)| id.0);
+        blocks
+    }
+
     // gets the block at a given position
     // If chunk is nonexistent, return None
     pub fn block_at(&self, pos : UniverseLocation) -> Option<BlockId> {
@@ -178,6 +490,171 @@ impl Universe {
             }
         }
     }
+
+    // follows the ray until it hits a block that isn't empty, returning where it hit and what block it was
+    // gives up at unloaded chunks, since we can't know what's in them
+    pub fn raycast_block(&self, from: &UniverseTransform, max_range: f64) -> Option<(RaycastHit, BlockId)> {
+        self.raycast_block_along(from.voxel_raycast(max_range))
+    }
+
+    // blocks that aren't cubes only count where the ray actually goes through one of their boxes,
+    // and the hit is moved onto that box so placing against a slab's top face goes above it
+    pub fn raycast_block_along(&self, ray: VoxelRaycast) -> Option<(RaycastHit, BlockId)> {
+        let (origin, direction) = (ray.origin(), ray.direction());
+        for hit in ray {
+            let id = self.block_at_int(hit.block)?;
+            let data = self.get_block_data_id(id);
+            match data.block_type {
+                // you reach through fluids to whatever's in them
+                BlockType::Empty | BlockType::Fluid => {}
+                _ if data.shape.is_cube() => return Some((hit, id)),
+                _ => {
+                    let corner = hit.block.as_dvec3();
+                    let nearest = self.block_boxes(id).iter()
+                        .filter_map(|b| ray_box_hit(origin, direction, corner + b.min.as_dvec3(), corner + b.max.as_dvec3()))
+                        .min_by(|a, b| a.0.total_cmp(&b.0));
+                    if let Some((distance, normal)) = nearest {
+                        let point = origin + direction * distance;
+                        return Some((RaycastHit { block: hit.block, normal, distance, point }, id));
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    // the boxes a block state takes up, in fractions of a block, see BlockShape::boxes
+    pub fn block_boxes(&self, id: BlockId) -> Vec<ShapeBox> {
+        let data = self.get_block_data_id(id);
+        match data.shape {
+            // most blocks, and they don't need their properties looked up
+            BlockShape::Cube => vec![ShapeBox::FULL],
+            _ => data.shape.boxes(&self.block_properties(id)),
+        }
+    }
+
+    // WORLD EDITING
+    // same as block_at, but for integer block positions
+    pub fn block_at_int(&self, pos: IVec3) -> Option<BlockId> {
+        let (cp, bp) = split_block_position(pos);
+        self.fetch_chunk(&cp)
+            .map(|c| Chunk::ref_from(c.as_ref()).unwrap().get(bp.x, bp.y, bp.z))
+    }
+
+    // EDITING
+    // everything that changes a block goes through edit_region, so every change gets recorded with its cause
+    // see the edits module for where they end up
+
+    pub fn record_edits(&self) {
+        self.edits.lock().get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_edits(&self) -> Vec<BlockEdit> {
+        self.edits.lock().as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // returns what was there before, or None if the chunk was never generated (and so nothing changed)
+    pub fn set_block(&self, pos: IVec3, block: BlockId, cause: EditCause) -> Option<BlockId> {
+        let mut old = None;
+        self.edit_region(&Region::from_corners(pos, pos), cause, |_, b| {
+            old = Some(b);
+            Some(block)
+        });
+        old
+    }
+
+    // calls edit on every block in the region, replacing the block with whatever it returns (if anything)
+    // every chunk is read and flushed at most once, and chunks that were never generated are skipped
+    // returns the positions of the chunks that actually changed, so callers can remesh each of them once
+    pub fn edit_region<F>(&self, region: &Region, cause: EditCause, mut edit: F) -> HashSet<IVec3>
+        where F: FnMut(IVec3, BlockId) -> Option<BlockId> {
+        let mut touched = HashSet::new();
+        let mut edits = self.edits.lock();
+        let (min_chunk, _) = split_block_position(region.min);
+        let (max_chunk, _) = split_block_position(region.max);
+
+        for cx in min_chunk.x..=max_chunk.x {
+            for cy in min_chunk.y..=max_chunk.y {
+                for cz in min_chunk.z..=max_chunk.z {
+                    let chunk_pos = IVec3::new(cx, cy, cz);
+                    let Some(c) = self.fetch_chunk(&chunk_pos) else {
+                        continue;
+                    };
+                    let mut chunk = Chunk::read_from(c.as_ref()).unwrap();
+                    let mut changed = false;
+                    // only kept track of when there are block entities that could be replaced
+                    let mut entities = self.chunk_block_entities(&chunk_pos);
+                    let mut changes = Vec::new();
+
+                    // the part of the region inside this chunk
+                    let chunk_min = chunk_pos * CHUNK_SIZE_I32;
+                    let chunk_max = chunk_min + IVec3::splat(CHUNK_SIZE_I32 - 1);
+                    let part = Region::from_corners(region.min.max(chunk_min), region.max.min(chunk_max));
+
+                    for pos in part.iter() {
+                        let local = (pos - chunk_min).as_uvec3();
+                        let old = chunk.get(local.x, local.y, local.z);
+                        match edit(pos, old) {
+                            Some(new) if new != old => {
+                                chunk.place(new, (local.x, local.y, local.z));
+                                changed = true;
+                                if let Some(edits) = edits.as_mut() {
+                                    edits.push(BlockEdit { pos, old, new, cause });
+                                }
+                                if !entities.0.is_empty() {
+                                    changes.push((pos, old, new));
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+
+                    if changed {
+                        self.flush_chunk(&chunk_pos, &chunk);
+                        touched.insert(chunk_pos);
+                        if forget_replaced(self, &mut entities, &changes) {
+                            self.store_chunk_block_entities(&chunk_pos, &entities);
+                        }
+                    }
+                }
+            }
+        }
+        touched
+    }
+
+    pub fn fill_region(&self, region: &Region, block: BlockId, cause: EditCause) -> HashSet<IVec3> {
+        self.edit_region(region, cause, |_, _| Some(block))
+    }
+
+    pub fn replace_in_region(&self, region: &Region, from: BlockId, to: BlockId, cause: EditCause) -> HashSet<IVec3> {
+        self.edit_region(region, cause, |_, old| (old == from).then_some(to))
+    }
+
+    // makes the outer layer of the region out of block and empties the inside
+    pub fn hollow_region(&self, region: &Region, block: BlockId, cause: EditCause) -> HashSet<IVec3> {
+        self.edit_region(region, cause, |pos, _| {
+            Some(if region.on_shell(pos) { block } else { AIR })
+        })
+    }
+
+    pub fn copy_region(&self, region: &Region) -> Clipboard {
+        let mut clipboard = Clipboard::new(region.size());
+        // never returns a block, so this only reads and the cause doesn't matter
+        self.edit_region(region, EditCause::Command, |pos, block| {
+            clipboard.set(pos - region.min, block);
+            None
+        });
+        clipboard
+    }
+
+    // pastes with the minimum corner of the clipboard at origin
+    // if skip_air is set, air in the clipboard leaves the world untouched instead of clearing it
+    pub fn paste(&self, clipboard: &Clipboard, origin: IVec3, skip_air: bool, cause: EditCause) -> HashSet<IVec3> {
+        self.edit_region(&clipboard.region_at(origin), cause, |pos, _| {
+            let block = clipboard.get(pos - origin);
+            (!skip_air || block != AIR).then_some(block)
+        })
+    }
 }
 
 impl Default for Universe {
@@ -0,0 +1,109 @@
+// a real server over 127.0.0.1, talked to with a bare Connection the way the game would
+
+use std::time::Duration;
+
+use bevy::math::f64::DVec3;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use dirlaku::chunk::chunk::{BlockId, AIR};
+use dirlaku::net::protocol::{ClientMessage, PlayerPose, ServerMessage, PROTOCOL_VERSION};
+use dirlaku::net::server::{Server, ServerPlugin, TICK_RATE};
+use dirlaku::net::Connection;
+use dirlaku::world::block::register_default_blocks;
+use dirlaku::world::edits::BlockEventsPlugin;
+use dirlaku::world::universe::Universe;
+
+// server ticks to wait on an answer before giving up
+const PATIENCE: usize = 300;
+
+fn server_app() -> (App, String) {
+    let path = std::env::temp_dir().join(format!("dirlaku-loopback-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let universe = Universe::open(path);
+    register_default_blocks(&universe);
+
+    let server = Server::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap().to_string();
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins((ServerPlugin, BlockEventsPlugin))
+        // every update is exactly one tick, however long it really took
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / TICK_RATE)))
+        .insert_resource(universe)
+        .insert_resource(server);
+    (app, address)
+}
+
+// runs the server until it sends something `want` picks out
+fn wait_for<T>(app: &mut App, conn: &mut Connection, mut want: impl FnMut(ServerMessage) -> Option<T>) -> T {
+    for _ in 0..PATIENCE {
+        conn.flush().unwrap();
+        app.update();
+        std::thread::sleep(Duration::from_millis(2));
+        for message in conn.receive::<ServerMessage>().unwrap() {
+            if let ServerMessage::Disconnect { reason } = &message {
+                panic!("kicked: {}", reason);
+            }
+            if let Some(found) = want(message) {
+                return found;
+            }
+        }
+    }
+    panic!("the server didn't send what we were waiting for");
+}
+
+fn pose_at(position: DVec3) -> ClientMessage {
+    ClientMessage::PlayerMove {
+        pose: PlayerPose { dimension: 0, position, pitch: 0.0, yaw: 0.0 },
+        horizontal_view: 1,
+        vertical_view: 1,
+    }
+}
+
+#[test]
+fn handshake_login_chunks_and_edits() {
+    let (mut app, address) = server_app();
+    let universe = app.world().resource::<Universe>().clone();
+    let mut conn = Connection::connect(&address).unwrap();
+
+    conn.send(&ClientMessage::Handshake { version: PROTOCOL_VERSION });
+    conn.send(&ClientMessage::Login { name: String::from("tester") });
+    let id = wait_for(&mut app, &mut conn, |m| match m {
+        ServerMessage::LoginSuccess { id, .. } => Some(id),
+        _ => None,
+    });
+    assert_eq!(id, 0);
+
+    // the chunk we're standing in comes first
+    conn.send(&pose_at(DVec3::splat(8.5)));
+    let coords = wait_for(&mut app, &mut conn, |m| match m {
+        ServerMessage::ChunkData { coords, .. } => Some(coords),
+        _ => None,
+    });
+    assert_eq!(coords, IVec3::ZERO);
+
+    let pos = IVec3::new(10, 8, 8);
+    let before = universe.block_at_int(pos).unwrap();
+    let block = if before == AIR { universe.try_block_id_from_name("stone").unwrap() } else { AIR };
+    conn.send(&ClientMessage::BlockChange { pos, block });
+    let changed = wait_for(&mut app, &mut conn, |m| match m {
+        ServerMessage::BlockChange { pos: p, block } if p == pos => Some(block),
+        _ => None,
+    });
+    assert_eq!(changed, block);
+    assert_eq!(universe.block_at_int(pos), Some(block));
+
+    // too far away, we get told what's really there and nothing changes
+    let far = IVec3::new(30, 8, 8);
+    let actual = universe.block_at_int(far).unwrap();
+    let wrong = if actual == AIR { universe.try_block_id_from_name("stone").unwrap() } else { AIR };
+    conn.send(&ClientMessage::BlockChange { pos: far, block: wrong });
+    let corrected: BlockId = wait_for(&mut app, &mut conn, |m| match m {
+        ServerMessage::BlockChange { pos: p, block } if p == far => Some(block),
+        _ => None,
+    });
+    assert_eq!(corrected, actual);
+    assert_eq!(universe.block_at_int(far), Some(actual));
+}